@@ -0,0 +1,173 @@
+use crate::database::Database;
+use crate::downloader::DownloadRequest;
+use crate::error::DownloadError;
+use serde::{Deserialize, Serialize};
+
+// Settings keys written by the settings page
+const AUDIO_FORMAT_KEY: &str = "audio_format";
+const AUDIO_BITRATE_KEY: &str = "audio_bitrate";
+
+/// Codecs yt-dlp can extract audio to
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioFormat {
+    Mp3,
+    M4a,
+    Aac,
+    Opus,
+    Flac,
+    Wav,
+    Vorbis,
+}
+
+impl AudioFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "mp3" => Some(AudioFormat::Mp3),
+            "m4a" => Some(AudioFormat::M4a),
+            "aac" => Some(AudioFormat::Aac),
+            "opus" => Some(AudioFormat::Opus),
+            "flac" => Some(AudioFormat::Flac),
+            "wav" => Some(AudioFormat::Wav),
+            "vorbis" | "ogg" => Some(AudioFormat::Vorbis),
+            _ => None,
+        }
+    }
+
+    /// Value passed to `--audio-format`
+    pub fn as_arg(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::M4a => "m4a",
+            AudioFormat::Aac => "aac",
+            AudioFormat::Opus => "opus",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Wav => "wav",
+            AudioFormat::Vorbis => "vorbis",
+        }
+    }
+
+    pub fn is_lossless(&self) -> bool {
+        matches!(self, AudioFormat::Flac | AudioFormat::Wav)
+    }
+
+    /// Range of constant bitrates (kbps) the encoder accepts
+    fn bitrate_range(&self) -> (u32, u32) {
+        match self {
+            AudioFormat::Mp3 => (32, 320),
+            AudioFormat::Opus => (6, 510),
+            AudioFormat::Vorbis => (45, 500),
+            _ => (32, 512),
+        }
+    }
+}
+
+/// Target quality for the extracted audio
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum AudioQuality {
+    /// Constant bitrate in kbps
+    Bitrate { kbps: u32 },
+    /// Encoder VBR level, 0 (best) to 10 (worst)
+    Vbr { level: u8 },
+}
+
+impl AudioQuality {
+    /// Parses the settings value: `"320"`, `"320k"` or `"v0"`..`"v10"`
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().to_lowercase();
+        if let Some(level) = value.strip_prefix('v') {
+            return level.parse().ok().map(|level| AudioQuality::Vbr { level });
+        }
+        value
+            .trim_end_matches("kbps")
+            .trim_end_matches('k')
+            .parse()
+            .ok()
+            .map(|kbps| AudioQuality::Bitrate { kbps })
+    }
+
+    /// Value passed to `--audio-quality`
+    pub fn as_arg(&self) -> String {
+        match self {
+            AudioQuality::Bitrate { kbps } => format!("{}K", kbps),
+            AudioQuality::Vbr { level } => level.to_string(),
+        }
+    }
+}
+
+/// Checks that the format and quality make sense together
+pub fn validate(format: AudioFormat, quality: Option<AudioQuality>) -> Result<(), DownloadError> {
+    match quality {
+        Some(_) if format.is_lossless() => Err(DownloadError::invalid(format!(
+            "{} is lossless and does not take a bitrate or VBR level",
+            format.as_arg()
+        ))),
+        Some(AudioQuality::Bitrate { kbps }) => {
+            let (min, max) = format.bitrate_range();
+            if kbps < min || kbps > max {
+                Err(DownloadError::invalid(format!(
+                    "{} bitrate must be between {} and {} kbps",
+                    format.as_arg(),
+                    min,
+                    max
+                )))
+            } else {
+                Ok(())
+            }
+        }
+        Some(AudioQuality::Vbr { level }) if level > 10 => {
+            Err(DownloadError::invalid("VBR level must be between 0 and 10"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Fills in the audio options the request leaves out from the saved settings, then validates them.
+///
+/// Only explicit options are rejected outright; a saved bitrate is dropped when it
+/// does not apply to the chosen format, since the settings page always stores one.
+pub fn resolve(db: &Database, request: &mut DownloadRequest) -> Result<(), DownloadError> {
+    if !request.audio_only {
+        return Ok(());
+    }
+
+    let format = match request.audio_format {
+        Some(format) => format,
+        None => db
+            .get_setting(AUDIO_FORMAT_KEY)
+            .ok()
+            .flatten()
+            .and_then(|value| AudioFormat::parse(&value))
+            .unwrap_or(AudioFormat::Mp3),
+    };
+
+    if request.audio_quality.is_some() {
+        validate(format, request.audio_quality)?;
+    } else {
+        request.audio_quality = db
+            .get_setting(AUDIO_BITRATE_KEY)
+            .ok()
+            .flatten()
+            .and_then(|value| AudioQuality::parse(&value))
+            .filter(|quality| validate(format, Some(*quality)).is_ok());
+    }
+
+    request.audio_format = Some(format);
+    Ok(())
+}
+
+/// yt-dlp arguments for audio extraction; without a quality the encoder's best VBR level is used
+pub fn extraction_args(format: Option<AudioFormat>, quality: Option<AudioQuality>) -> Vec<String> {
+    let format = format.unwrap_or(AudioFormat::Mp3);
+    let mut args = vec![
+        "-x".to_string(),
+        "--audio-format".to_string(),
+        format.as_arg().to_string(),
+    ];
+    if !format.is_lossless() {
+        let quality = quality.unwrap_or(AudioQuality::Vbr { level: 0 });
+        args.extend(["--audio-quality".to_string(), quality.as_arg()]);
+    }
+    args
+}
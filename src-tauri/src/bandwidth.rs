@@ -0,0 +1,135 @@
+use crate::commands::AppState;
+use crate::database::Database;
+use crate::scheduler::Scheduler;
+use chrono::{Local, NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+// Settings key the bandwidth settings are persisted under
+const BANDWIDTH_KEY: &str = "bandwidth";
+
+/// A daily time window with its own limit; windows may wrap past midnight
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BandwidthWindow {
+    /// "HH:MM", local time
+    pub start: String,
+    /// "HH:MM", local time, exclusive
+    pub end: String,
+    /// Bytes per second; `None` lifts the limit during this window
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct BandwidthSettings {
+    /// Bytes per second applied outside every scheduled window; `None` is unlimited
+    pub limit: Option<u64>,
+    #[serde(default)]
+    pub schedule: Vec<BandwidthWindow>,
+}
+
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").ok()
+}
+
+impl BandwidthWindow {
+    fn contains(&self, time: NaiveTime) -> bool {
+        let (Some(start), Some(end)) = (parse_time(&self.start), parse_time(&self.end)) else {
+            return false;
+        };
+        if start <= end {
+            time >= start && time < end
+        } else {
+            time >= start || time < end
+        }
+    }
+}
+
+impl BandwidthSettings {
+    pub fn load(db: &Database) -> Self {
+        db.get_setting(BANDWIDTH_KEY)
+            .ok()
+            .flatten()
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.limit == Some(0) {
+            return Err("Bandwidth limit must be greater than zero; leave it empty for unlimited".to_string());
+        }
+        for window in &self.schedule {
+            let (Some(start), Some(end)) = (parse_time(&window.start), parse_time(&window.end)) else {
+                return Err(format!(
+                    "Invalid schedule window {}-{}: times must be HH:MM",
+                    window.start, window.end
+                ));
+            };
+            if start == end {
+                return Err(format!("Schedule window {}-{} is empty", window.start, window.end));
+            }
+            if window.limit == Some(0) {
+                return Err("Scheduled limits must be greater than zero; leave them empty for unlimited".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// The limit in force at `time`: the first matching window wins, then the global limit
+    pub fn limit_at(&self, time: NaiveTime) -> Option<u64> {
+        match self.schedule.iter().find(|window| window.contains(time)) {
+            Some(window) => window.limit,
+            None => self.limit,
+        }
+    }
+
+    pub fn current_limit(&self) -> Option<u64> {
+        let now = Local::now();
+        // Minute precision, matching the schedule format
+        let time = NaiveTime::from_hms_opt(now.hour(), now.minute(), 0).unwrap_or_default();
+        self.limit_at(time)
+    }
+}
+
+/// Re-checks the schedule every minute so running downloads pick up a new window's limit
+pub fn start_schedule_timer(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            let scheduler = app.state::<Scheduler>();
+            scheduler.apply_bandwidth(&app);
+        }
+    });
+}
+
+// Tauri commands for bandwidth throttling
+#[tauri::command]
+pub async fn get_bandwidth_settings(state: State<'_, AppState>) -> Result<BandwidthSettings, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    Ok(BandwidthSettings::load(&db))
+}
+
+#[tauri::command]
+pub async fn set_bandwidth_settings(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    scheduler: State<'_, Scheduler>,
+    settings: BandwidthSettings,
+) -> Result<(), String> {
+    settings.validate()?;
+    {
+        let serialized = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        db.save_setting(BANDWIDTH_KEY, &serialized).map_err(|e| e.to_string())?;
+    }
+
+    scheduler.set_bandwidth(settings);
+    scheduler.apply_bandwidth(&app_handle);
+    Ok(())
+}
+
+/// The limit new downloads would be launched with right now, in bytes per second
+#[tauri::command]
+pub async fn get_current_bandwidth_limit(scheduler: State<'_, Scheduler>) -> Result<Option<u64>, String> {
+    Ok(scheduler.current_bandwidth_limit())
+}
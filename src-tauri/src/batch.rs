@@ -0,0 +1,439 @@
+use crate::commands::AppState;
+use crate::downloader::DownloadRequest;
+use crate::error::DownloadError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+use uuid::Uuid;
+
+const QUALITIES: [&str; 7] = ["best", "4k", "2160p", "1080p", "720p", "480p", "360p"];
+
+/// One entry of a batch file before validation
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct BatchEntry {
+    pub url: String,
+    pub quality: Option<String>,
+    pub format: Option<String>,
+    #[serde(alias = "output")]
+    pub output_path: Option<String>,
+    pub audio_only: Option<bool>,
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchLineStatus {
+    Accepted,
+    Rejected,
+    Duplicate,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatchLineReport {
+    /// Line number for text/CSV files, item number for JSON
+    pub line: usize,
+    pub url: Option<String>,
+    pub status: BatchLineStatus,
+    pub reason: Option<String>,
+    /// Download id assigned to accepted entries
+    pub id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatchImportReport {
+    pub entries: Vec<BatchLineReport>,
+    pub accepted: usize,
+    pub rejected: usize,
+    pub duplicates: usize,
+    pub enqueued: bool,
+}
+
+/// Entries numbered by line (or item), each either parsed or carrying the parse failure
+type ParsedEntries = Vec<(usize, Result<BatchEntry, String>)>;
+
+/// Parses a batch file into numbered entries; a per-line parse failure becomes an `Err` entry
+fn parse_file(path: &Path, contents: &str) -> Result<ParsedEntries, String> {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "json" => parse_json(contents),
+        "csv" => Ok(parse_csv(contents)),
+        _ => Ok(parse_text(contents)),
+    }
+}
+
+fn parse_text(contents: &str) -> ParsedEntries {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(number, line)| {
+            (number, Ok(BatchEntry { url: line.to_string(), ..Default::default() }))
+        })
+        .collect()
+}
+
+fn parse_json(contents: &str) -> Result<ParsedEntries, String> {
+    let items: Vec<serde_json::Value> = serde_json::from_str(contents)
+        .map_err(|e| format!("Expected a JSON array of requests: {}", e))?;
+
+    Ok(items
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let entry = match item {
+                serde_json::Value::String(url) => Ok(BatchEntry { url, ..Default::default() }),
+                other => serde_json::from_value::<BatchEntry>(other).map_err(|e| e.to_string()),
+            };
+            (index + 1, entry)
+        })
+        .collect())
+}
+
+fn parse_csv(contents: &str) -> ParsedEntries {
+    let mut rows = contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line))
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+
+    // Use the header when there is one, otherwise assume url,quality,format,output
+    let mut columns: Vec<String> = ["url", "quality", "format", "output"]
+        .iter()
+        .map(|c| c.to_string())
+        .collect();
+    let mut pending_first = None;
+    if let Some((number, first)) = rows.next() {
+        let fields = split_csv_line(first);
+        if fields.iter().any(|f| f.trim().eq_ignore_ascii_case("url")) {
+            columns = fields.iter().map(|f| f.trim().to_lowercase()).collect();
+        } else {
+            pending_first = Some((number, first));
+        }
+    }
+
+    pending_first
+        .into_iter()
+        .chain(rows)
+        .map(|(number, line)| {
+            let mut entry = BatchEntry::default();
+            for (column, value) in columns.iter().zip(split_csv_line(line)) {
+                let value = value.trim().to_string();
+                if value.is_empty() {
+                    continue;
+                }
+                match column.as_str() {
+                    "url" => entry.url = value,
+                    "quality" => entry.quality = Some(value),
+                    "format" => entry.format = Some(value),
+                    "output" | "output_path" => entry.output_path = Some(value),
+                    "title" => entry.title = Some(value),
+                    "audio_only" => match parse_bool(&value) {
+                        Some(flag) => entry.audio_only = Some(flag),
+                        None => return (number, Err(format!("Invalid audio_only value: {}", value))),
+                    },
+                    _ => {}
+                }
+            }
+            (number, Ok(entry))
+        })
+        .collect()
+}
+
+/// Splits a CSV line, honouring double-quoted fields and `""` escapes
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    fields.push(current);
+    fields
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "1" => Some(true),
+        "false" | "no" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn validate_url(url: &str) -> Result<(), String> {
+    if url.is_empty() {
+        return Err("Missing URL".to_string());
+    }
+    if url.chars().any(char::is_whitespace) {
+        return Err("URL contains whitespace".to_string());
+    }
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .ok_or_else(|| "URL must start with http:// or https://".to_string())?;
+    let host = rest.split(['/', '?', '#']).next().unwrap_or("");
+    if host.is_empty() || (!host.contains('.') && !host.starts_with("localhost")) {
+        return Err("URL has no valid host".to_string());
+    }
+    Ok(())
+}
+
+fn validate_entry(entry: &BatchEntry) -> Result<(), String> {
+    validate_url(&entry.url)?;
+    if let Some(quality) = &entry.quality {
+        if !QUALITIES.contains(&quality.to_lowercase().as_str()) {
+            return Err(format!("Unknown quality: {}", quality));
+        }
+    }
+    Ok(())
+}
+
+/// Validates and deduplicates parsed entries against `seen`, the canonical ids already
+/// downloaded, building a request from `template` for each accepted one
+fn build_report(
+    parsed: ParsedEntries,
+    template: &DownloadRequest,
+    seen: &mut HashSet<String>,
+) -> (BatchImportReport, Vec<DownloadRequest>) {
+    let mut report = BatchImportReport {
+        entries: Vec::with_capacity(parsed.len()),
+        accepted: 0,
+        rejected: 0,
+        duplicates: 0,
+        enqueued: false,
+    };
+    let mut requests = Vec::new();
+
+    for (line, entry) in parsed {
+        let entry = entry.and_then(|entry| validate_entry(&entry).map(|_| entry));
+        let line_report = match entry {
+            Err(reason) => {
+                report.rejected += 1;
+                BatchLineReport { line, url: None, status: BatchLineStatus::Rejected, reason: Some(reason), id: None }
+            }
+            Ok(entry) if !seen.insert(crate::canonical::canonical_id(&entry.url)) => {
+                report.duplicates += 1;
+                BatchLineReport {
+                    line,
+                    url: Some(entry.url),
+                    status: BatchLineStatus::Duplicate,
+                    reason: Some("Media was already downloaded or appears earlier in this file".to_string()),
+                    id: None,
+                }
+            }
+            Ok(entry) => {
+                report.accepted += 1;
+                let request = DownloadRequest {
+                    id: Uuid::new_v4().to_string(),
+                    url: entry.url.clone(),
+                    output_path: entry.output_path.unwrap_or_else(|| template.output_path.clone()),
+                    format: entry.format.or_else(|| template.format.clone()),
+                    audio_only: entry.audio_only.unwrap_or(template.audio_only),
+                    quality: entry.quality.map(|q| q.to_lowercase()).or_else(|| template.quality.clone()),
+                    title: entry.title,
+                    ..template.clone()
+                };
+                let id = request.id.clone();
+                requests.push(request);
+                BatchLineReport { line, url: Some(entry.url), status: BatchLineStatus::Accepted, reason: None, id: Some(id) }
+            }
+        };
+        report.entries.push(line_report);
+    }
+
+    (report, requests)
+}
+
+/// Validates and deduplicates every entry of a batch file, then queues the accepted ones.
+///
+/// `template` supplies the default download options; per-entry columns override them.
+/// With `dry_run` set only the report is produced.
+#[tauri::command]
+pub async fn import_batch(
+    app_handle: AppHandle,
+    path: String,
+    template: DownloadRequest,
+    dry_run: Option<bool>,
+) -> Result<BatchImportReport, String> {
+    let path = Path::new(&path);
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read batch file: {}", e))?;
+    let parsed = parse_file(path, &contents)?;
+
+    let mut seen: HashSet<String> = {
+        let state = app_handle.state::<AppState>();
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        // Options shared by every entry are checked once, before anything is queued
+        crate::queue::validate(&db, &mut template.clone()).map_err(|e| e.to_string())?;
+        db.get_completed_canonical_ids()
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .collect()
+    };
+
+    let (mut report, requests) = build_report(parsed, &template, &mut seen);
+
+    if !dry_run.unwrap_or(false) {
+        for request in requests {
+            let id = request.id.clone();
+            match crate::queue::enqueue(&app_handle, request) {
+                Ok(()) => {}
+                // Only caught once yt-dlp identified the media, so report it like any other duplicate
+                Err(DownloadError::Duplicate { message, .. }) => {
+                    if let Some(entry) = report.entries.iter_mut().find(|entry| entry.id.as_deref() == Some(&id)) {
+                        entry.status = BatchLineStatus::Duplicate;
+                        entry.reason = Some(message);
+                        entry.id = None;
+                    }
+                    report.accepted -= 1;
+                    report.duplicates += 1;
+                }
+                // Earlier entries are already queued, so a failing entry is reported rather than
+                // aborting the rest
+                Err(e) => {
+                    if let Some(entry) = report.entries.iter_mut().find(|entry| entry.id.as_deref() == Some(&id)) {
+                        entry.status = BatchLineStatus::Rejected;
+                        entry.reason = Some(e.to_string());
+                        entry.id = None;
+                    }
+                    report.accepted -= 1;
+                    report.rejected += 1;
+                }
+            }
+        }
+        report.enqueued = true;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template() -> DownloadRequest {
+        serde_json::from_value(serde_json::json!({
+            "id": "template",
+            "url": "",
+            "output_path": "/downloads",
+            "audio_only": false,
+            "embed_thumbnail": false,
+            "embed_metadata": false,
+        }))
+        .unwrap()
+    }
+
+    fn urls(entries: &ParsedEntries) -> Vec<(usize, String)> {
+        entries
+            .iter()
+            .map(|(line, entry)| (*line, entry.as_ref().map(|e| e.url.clone()).unwrap_or_default()))
+            .collect()
+    }
+
+    #[test]
+    fn text_skips_blank_and_comment_lines() {
+        let parsed = parse_text("# my list\n\nhttps://a.example/1\n   \n  https://a.example/2  \n#https://a.example/3\n");
+        assert_eq!(
+            urls(&parsed),
+            [(3, "https://a.example/1".to_string()), (5, "https://a.example/2".to_string())]
+        );
+    }
+
+    #[test]
+    fn csv_with_header_maps_columns_by_name() {
+        let parsed = parse_csv("Title,URL,audio_only\nFirst,https://a.example/1,yes\n,https://a.example/2,no\n");
+        assert_eq!(parsed.len(), 2);
+        let first = parsed[0].1.as_ref().unwrap();
+        assert_eq!(parsed[0].0, 2);
+        assert_eq!(first.url, "https://a.example/1");
+        assert_eq!(first.title.as_deref(), Some("First"));
+        assert_eq!(first.audio_only, Some(true));
+        let second = parsed[1].1.as_ref().unwrap();
+        assert_eq!(second.title, None);
+        assert_eq!(second.audio_only, Some(false));
+    }
+
+    #[test]
+    fn csv_without_header_uses_default_columns() {
+        let parsed = parse_csv("https://a.example/1,720p,,/videos\nhttps://a.example/2\n");
+        assert_eq!(urls(&parsed), [(1, "https://a.example/1".to_string()), (2, "https://a.example/2".to_string())]);
+        let first = parsed[0].1.as_ref().unwrap();
+        assert_eq!(first.quality.as_deref(), Some("720p"));
+        assert_eq!(first.format, None);
+        assert_eq!(first.output_path.as_deref(), Some("/videos"));
+    }
+
+    #[test]
+    fn csv_honours_quoted_fields() {
+        assert_eq!(
+            split_csv_line(r#""https://a.example/v?x=1,2","Say ""hi"", then go",plain"#),
+            ["https://a.example/v?x=1,2", r#"Say "hi", then go"#, "plain"]
+        );
+        let parsed = parse_csv("url,title\n\"https://a.example/1\",\"One, two\"\n");
+        assert_eq!(parsed[0].1.as_ref().unwrap().title.as_deref(), Some("One, two"));
+    }
+
+    #[test]
+    fn csv_skips_blank_and_comment_lines_and_reports_bad_values() {
+        let parsed = parse_csv("url,audio_only\n\n# comment\nhttps://a.example/1,maybe\n");
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].0, 4);
+        assert!(parsed[0].1.as_ref().unwrap_err().contains("maybe"));
+    }
+
+    #[test]
+    fn json_accepts_strings_and_objects() {
+        let parsed = parse_json(r#"["https://a.example/1", {"url": "https://a.example/2", "output": "/v"}, 5]"#).unwrap();
+        assert_eq!(parsed.len(), 3);
+        assert_eq!(parsed[0].1.as_ref().unwrap().url, "https://a.example/1");
+        assert_eq!(parsed[1].1.as_ref().unwrap().output_path.as_deref(), Some("/v"));
+        assert_eq!(parsed[2].0, 3);
+        assert!(parsed[2].1.is_err());
+        assert!(parse_json(r#"{"url": "https://a.example/1"}"#).is_err());
+    }
+
+    #[test]
+    fn duplicates_are_found_by_canonical_id() {
+        let parsed = parse_text(
+            "https://youtu.be/dQw4w9WgXcQ\n\
+             https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=5\n\
+             https://vimeo.com/123456\n\
+             not a url\n\
+             https://a.example/1?utm_source=x\n\
+             https://a.example/1\n",
+        );
+        let mut seen: HashSet<String> = ["vimeo:123456".to_string()].into_iter().collect();
+        let (report, requests) = build_report(parsed, &template(), &mut seen);
+
+        let statuses: Vec<BatchLineStatus> = report.entries.iter().map(|entry| entry.status.clone()).collect();
+        assert_eq!(
+            statuses,
+            [
+                BatchLineStatus::Accepted,
+                BatchLineStatus::Duplicate,
+                BatchLineStatus::Duplicate,
+                BatchLineStatus::Rejected,
+                BatchLineStatus::Accepted,
+                BatchLineStatus::Duplicate,
+            ]
+        );
+        assert_eq!((report.accepted, report.duplicates, report.rejected), (2, 3, 1));
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].output_path, "/downloads");
+    }
+}
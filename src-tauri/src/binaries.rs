@@ -0,0 +1,624 @@
+use crate::commands::AppState;
+use crate::database::Database;
+use crate::downloader::Downloader;
+use crate::network::NetworkSettings;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::env::consts::EXE_SUFFIX;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+// Settings keys for the release sources and what is currently installed
+pub(crate) const SOURCES_KEY: &str = "binary_sources";
+pub(crate) const INSTALLED_KEY: &str = "installed_binaries";
+
+const YT_DLP_RELEASES: &str = "https://github.com/yt-dlp/yt-dlp/releases/latest/download";
+const YT_DLP_CHECKSUMS: &str = "SHA2-256SUMS";
+// Emit at most one progress event per this many bytes when the size is unknown
+const PROGRESS_STEP_BYTES: u64 = 1024 * 1024;
+
+// Tools with an install in progress, so two installs can't race on the same files
+lazy_static::lazy_static! {
+    static ref INSTALLING: Mutex<HashSet<Tool>> = Mutex::new(HashSet::new());
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Tool {
+    YtDlp,
+    Ffmpeg,
+    Ffprobe,
+}
+
+impl Tool {
+    const ALL: [Tool; 3] = [Tool::YtDlp, Tool::Ffmpeg, Tool::Ffprobe];
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Tool::YtDlp => "yt-dlp",
+            Tool::Ffmpeg => "ffmpeg",
+            Tool::Ffprobe => "ffprobe",
+        }
+    }
+
+    /// Name inside `binaries`, matching what the toolchain looks for
+    pub(crate) fn file_name(self) -> String {
+        format!("{}{}", self.label(), EXE_SUFFIX)
+    }
+
+    /// Name of the release asset for this platform
+    fn asset_name(self) -> String {
+        match self {
+            Tool::YtDlp if cfg!(windows) => "yt-dlp.exe".to_string(),
+            Tool::YtDlp if cfg!(target_os = "macos") => "yt-dlp_macos".to_string(),
+            // The plain "yt-dlp" asset is a zipapp that needs a system Python
+            Tool::YtDlp if cfg!(all(target_os = "linux", target_arch = "aarch64")) => "yt-dlp_linux_aarch64".to_string(),
+            Tool::YtDlp if cfg!(target_os = "linux") => "yt-dlp_linux".to_string(),
+            tool => tool.file_name(),
+        }
+    }
+
+    pub(crate) fn version_arg(self) -> &'static str {
+        match self {
+            Tool::YtDlp => "--version",
+            Tool::Ffmpeg | Tool::Ffprobe => "-version",
+        }
+    }
+}
+
+/// Where releases are fetched from: a folder holding the assets and a checksum file
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BinarySource {
+    /// An http(s) URL, a file:// URL or a local directory, e.g. a mirror on a network share
+    pub base: String,
+    /// Checksum file in `sha256sum` format, relative to `base`
+    pub checksums: String,
+}
+
+impl Default for BinarySource {
+    fn default() -> Self {
+        Self {
+            base: YT_DLP_RELEASES.to_string(),
+            checksums: YT_DLP_CHECKSUMS.to_string(),
+        }
+    }
+}
+
+impl BinarySource {
+    fn validate(&self) -> Result<(), String> {
+        let base = self.base.trim();
+        let is_url = base.starts_with("https://") || base.starts_with("http://") || base.starts_with("file://");
+        if !is_url && !Path::new(base).is_absolute() {
+            return Err(format!("Release source '{}' must be a URL or an absolute folder path", base));
+        }
+        let checksums = self.checksums.trim();
+        if checksums.is_empty() || checksums.starts_with('/') || checksums.split(['/', '\\']).any(|part| part == "..") {
+            return Err("Checksum file must be a name relative to the release source".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct BinarySourceSettings {
+    pub yt_dlp: BinarySource,
+    /// Serves both ffmpeg and ffprobe as plain binaries; there is no default since the
+    /// official builds only ship archives
+    pub ffmpeg: Option<BinarySource>,
+}
+
+impl BinarySourceSettings {
+    pub fn load(db: &Database) -> Self {
+        db.get_setting(SOURCES_KEY)
+            .ok()
+            .flatten()
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.yt_dlp.validate()?;
+        if let Some(ffmpeg) = &self.ffmpeg {
+            ffmpeg.validate()?;
+        }
+        Ok(())
+    }
+
+    fn source_for(&self, tool: Tool) -> Option<&BinarySource> {
+        match tool {
+            Tool::YtDlp => Some(&self.yt_dlp),
+            Tool::Ffmpeg | Tool::Ffprobe => self.ffmpeg.as_ref(),
+        }
+    }
+}
+
+/// One installed version of a tool
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BinaryRecord {
+    pub version: String,
+    pub sha256: String,
+    /// Where the file was fetched from
+    pub source: String,
+    pub installed_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+struct InstalledVersions {
+    current: Option<BinaryRecord>,
+    previous: Option<BinaryRecord>,
+}
+
+type InstalledBinaries = BTreeMap<Tool, InstalledVersions>;
+
+fn load_installed(db: &Database) -> InstalledBinaries {
+    db.get_setting(INSTALLED_KEY)
+        .ok()
+        .flatten()
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default()
+}
+
+fn save_installed(app_handle: &AppHandle, installed: &InstalledBinaries) -> Result<(), String> {
+    let serialized = serde_json::to_string(installed).map_err(|e| e.to_string())?;
+    let state = app_handle.state::<AppState>();
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.save_setting(INSTALLED_KEY, &serialized).map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BinaryStatus {
+    pub tool: Tool,
+    /// Path of the managed copy in the app data `binaries` folder, if there is one
+    pub path: Option<String>,
+    pub current: Option<BinaryRecord>,
+    pub previous: Option<BinaryRecord>,
+    pub can_rollback: bool,
+    /// Whether a release source is configured for this tool
+    pub installable: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallStage {
+    Downloading,
+    Verifying,
+    Installing,
+    Completed,
+    RolledBack,
+    Failed,
+}
+
+/// Payload of the `binary-install-progress` event
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BinaryInstallProgress {
+    pub tool: Tool,
+    pub stage: InstallStage,
+    pub downloaded_bytes: u64,
+    pub total_bytes: Option<u64>,
+    pub version: Option<String>,
+    pub error: Option<String>,
+}
+
+fn emit_progress(app_handle: &AppHandle, tool: Tool, stage: InstallStage, downloaded_bytes: u64, total_bytes: Option<u64>) {
+    let _ = app_handle.emit("binary-install-progress", BinaryInstallProgress {
+        tool,
+        stage,
+        downloaded_bytes,
+        total_bytes,
+        version: None,
+        error: None,
+    });
+}
+
+/// Marks a tool as being installed until dropped
+struct InstallGuard(Tool);
+
+impl InstallGuard {
+    fn acquire(tool: Tool) -> Result<Self, String> {
+        let mut installing = INSTALLING.lock().map_err(|e| e.to_string())?;
+        if !installing.insert(tool) {
+            return Err(format!("{} is already being installed", tool.label()));
+        }
+        Ok(Self(tool))
+    }
+}
+
+impl Drop for InstallGuard {
+    fn drop(&mut self) {
+        if let Ok(mut installing) = INSTALLING.lock() {
+            installing.remove(&self.0);
+        }
+    }
+}
+
+fn binaries_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    let app_data_dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(app_data_dir.join("binaries"))
+}
+
+/// Staging and rollback copies sit next to the binary so renames stay on one filesystem;
+/// the executable suffix is kept so Windows will still run them
+fn sibling(dir: &Path, tool: Tool, tag: &str) -> PathBuf {
+    dir.join(format!("{}.{}{}", tool.label(), tag, EXE_SUFFIX))
+}
+
+enum Location {
+    Remote(String),
+    Local(PathBuf),
+}
+
+impl Location {
+    fn resolve(base: &str, name: &str) -> Self {
+        let base = base.trim().trim_end_matches(['/', '\\']);
+        if base.starts_with("http://") || base.starts_with("https://") {
+            return Location::Remote(format!("{}/{}", base, name));
+        }
+        let path = base.strip_prefix("file://").unwrap_or(base);
+        // file:///C:/mirror has a slash before the drive letter
+        let path = match path.strip_prefix('/') {
+            Some(rest) if cfg!(windows) && rest.chars().nth(1) == Some(':') => rest,
+            _ => path,
+        };
+        Location::Local(Path::new(path).join(name))
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Location::Remote(url) => url.clone(),
+            Location::Local(path) => path.to_string_lossy().to_string(),
+        }
+    }
+}
+
+/// A client that goes through the same proxy and user agent as yt-dlp
+fn http_client(network: &NetworkSettings) -> Result<reqwest::Client, String> {
+    let mut builder = reqwest::Client::builder()
+        .connect_timeout(std::time::Duration::from_secs(30))
+        .user_agent(network.user_agent.clone().unwrap_or_else(|| "Slasshy-OmniDownloader".to_string()));
+    if let Some(proxy) = network.proxy.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+        builder = builder.proxy(reqwest::Proxy::all(proxy).map_err(|e| e.to_string())?);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+async fn read_text(client: &reqwest::Client, location: &Location) -> Result<String, String> {
+    let result = match location {
+        Location::Remote(url) => match client.get(url).send().await.and_then(|r| r.error_for_status()) {
+            Ok(response) => response.text().await.map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        },
+        Location::Local(path) => tokio::fs::read_to_string(path).await.map_err(|e| e.to_string()),
+    };
+    result.map_err(|e| format!("Failed to read {}: {}", location.describe(), e))
+}
+
+/// Finds the expected hash for `asset` in a `sha256sum` style listing
+fn find_checksum(listing: &str, asset: &str) -> Option<String> {
+    listing.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        let hash = parts.next()?;
+        let name = parts.next()?.trim_start_matches('*');
+        let valid = hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit());
+        (valid && name == asset).then(|| hash.to_lowercase())
+    })
+}
+
+/// Copies `location` to `destination`, returning the SHA-256 of what was written
+async fn fetch(
+    client: &reqwest::Client,
+    location: &Location,
+    destination: &Path,
+    mut on_progress: impl FnMut(u64, Option<u64>),
+) -> Result<String, String> {
+    let mut file = tokio::fs::File::create(destination).await.map_err(|e| e.to_string())?;
+    let mut hasher = Sha256::new();
+    let mut downloaded = 0u64;
+
+    match location {
+        Location::Remote(url) => {
+            let mut response = client
+                .get(url)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+            let total = response.content_length();
+            while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+                hasher.update(&chunk);
+                file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+                downloaded += chunk.len() as u64;
+                on_progress(downloaded, total);
+            }
+        }
+        Location::Local(path) => {
+            let mut source = tokio::fs::File::open(path)
+                .await
+                .map_err(|e| format!("Failed to open {:?}: {}", path, e))?;
+            let total = source.metadata().await.ok().map(|metadata| metadata.len());
+            let mut buffer = vec![0u8; 64 * 1024];
+            loop {
+                let read = source.read(&mut buffer).await.map_err(|e| e.to_string())?;
+                if read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..read]);
+                file.write_all(&buffer[..read]).await.map_err(|e| e.to_string())?;
+                downloaded += read as u64;
+                on_progress(downloaded, total);
+            }
+        }
+    }
+
+    file.sync_all().await.map_err(|e| e.to_string())?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn make_executable(path: &Path) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).map_err(|e| e.to_string())?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Runs the binary to make sure it works on this machine and reports its version
+async fn probe_version(path: &Path, tool: Tool) -> Result<String, String> {
+    let output = Downloader::create_hidden_command(&path.to_string_lossy())
+        .arg(tool.version_arg())
+        .output()
+        .await
+        .map_err(|e| format!("Downloaded {} does not run: {}", tool.label(), e))?;
+    if !output.status.success() {
+        return Err(format!("Downloaded {} exited with {}", tool.label(), output.status));
+    }
+
+    Ok(version_from_output(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// The version from `--version` / `-version` output
+pub(crate) fn version_from_output(stdout: &str) -> String {
+    let first_line = stdout.lines().next().unwrap_or("").trim();
+    // ffmpeg prints "ffmpeg version 7.0.1 Copyright ..."
+    let version = match first_line.split_once(" version ") {
+        Some((_, rest)) => rest.split_whitespace().next().unwrap_or(rest),
+        None => first_line,
+    };
+    version.to_string()
+}
+
+/// Moves the staged binary into place, keeping the one it replaces as the rollback copy.
+/// Returns whether there was a binary to keep.
+fn swap_in(dir: &Path, tool: Tool, staged: &Path) -> Result<bool, String> {
+    let target = dir.join(tool.file_name());
+    let previous = sibling(dir, tool, "previous");
+    let had_current = target.exists();
+
+    if had_current {
+        // Windows refuses to rename over an existing file
+        let _ = std::fs::remove_file(&previous);
+        std::fs::rename(&target, &previous).map_err(|e| format!("Failed to keep the current version: {}", e))?;
+    }
+    if let Err(e) = std::fs::rename(staged, &target) {
+        if had_current {
+            let _ = std::fs::rename(&previous, &target);
+        }
+        return Err(format!("Failed to install {}: {}", tool.label(), e));
+    }
+    Ok(had_current)
+}
+
+async fn install(app_handle: &AppHandle, tool: Tool) -> Result<BinaryRecord, String> {
+    let _guard = InstallGuard::acquire(tool)?;
+    let (sources, network) = {
+        let state = app_handle.state::<AppState>();
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        (BinarySourceSettings::load(&db), NetworkSettings::load(&db))
+    };
+    let source = sources
+        .source_for(tool)
+        .ok_or_else(|| format!("No release source is configured for {}", tool.label()))?;
+    let dir = binaries_dir(app_handle)?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let client = http_client(&network)?;
+
+    emit_progress(app_handle, tool, InstallStage::Downloading, 0, None);
+    let asset = tool.asset_name();
+    let checksums = Location::resolve(&source.base, source.checksums.trim());
+    let expected = find_checksum(&read_text(&client, &checksums).await?, &asset)
+        .ok_or_else(|| format!("{} is not listed in {}", asset, checksums.describe()))?;
+
+    let location = Location::resolve(&source.base, &asset);
+    let staged = sibling(&dir, tool, "download");
+    let mut last_emitted = 0u64;
+    let fetched = fetch(&client, &location, &staged, |downloaded, total| {
+        let due = match total {
+            Some(total) if total > 0 => downloaded * 100 / total > last_emitted * 100 / total,
+            _ => downloaded - last_emitted >= PROGRESS_STEP_BYTES,
+        };
+        if due {
+            last_emitted = downloaded;
+            emit_progress(app_handle, tool, InstallStage::Downloading, downloaded, total);
+        }
+    })
+    .await;
+
+    // Nothing touches the installed binary until the new one is verified
+    let verified = async {
+        let actual = fetched?;
+        emit_progress(app_handle, tool, InstallStage::Verifying, last_emitted, None);
+        if actual != expected {
+            return Err(format!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                asset, expected, actual
+            ));
+        }
+        make_executable(&staged)?;
+        let version = probe_version(&staged, tool).await?;
+        Ok((actual, version))
+    }
+    .await;
+    let (sha256, version) = match verified {
+        Ok(verified) => verified,
+        Err(e) => {
+            let _ = std::fs::remove_file(&staged);
+            return Err(e);
+        }
+    };
+
+    emit_progress(app_handle, tool, InstallStage::Installing, last_emitted, None);
+    let kept_previous = swap_in(&dir, tool, &staged)?;
+
+    let record = BinaryRecord {
+        version,
+        sha256,
+        source: location.describe(),
+        installed_at: chrono::Utc::now().timestamp_millis(),
+    };
+    let mut installed = {
+        let state = app_handle.state::<AppState>();
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        load_installed(&db)
+    };
+    let versions = installed.entry(tool).or_default();
+    if kept_previous {
+        versions.previous = versions.current.take();
+    }
+    versions.current = Some(record.clone());
+    save_installed(app_handle, &installed)?;
+
+    println!("[Binaries] Installed {} {}", tool.label(), record.version);
+    Ok(record)
+}
+
+fn rollback(app_handle: &AppHandle, tool: Tool) -> Result<Option<BinaryRecord>, String> {
+    let _guard = InstallGuard::acquire(tool)?;
+    let dir = binaries_dir(app_handle)?;
+    let target = dir.join(tool.file_name());
+    let previous = sibling(&dir, tool, "previous");
+    let swap = sibling(&dir, tool, "rollback");
+    if !previous.exists() {
+        return Err(format!("There is no previous version of {} to roll back to", tool.label()));
+    }
+
+    // The version being rolled back from becomes the new rollback copy
+    let had_current = target.exists();
+    if had_current {
+        let _ = std::fs::remove_file(&swap);
+        std::fs::rename(&target, &swap).map_err(|e| e.to_string())?;
+    }
+    if let Err(e) = std::fs::rename(&previous, &target) {
+        if had_current {
+            let _ = std::fs::rename(&swap, &target);
+        }
+        return Err(format!("Failed to restore the previous {}: {}", tool.label(), e));
+    }
+    if had_current {
+        std::fs::rename(&swap, &previous).map_err(|e| e.to_string())?;
+    }
+
+    let mut installed = {
+        let state = app_handle.state::<AppState>();
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        load_installed(&db)
+    };
+    let versions = installed.entry(tool).or_default();
+    let restored = versions.previous.take();
+    versions.previous = if had_current { versions.current.take() } else { None };
+    versions.current = restored.clone();
+    save_installed(app_handle, &installed)?;
+
+    println!("[Binaries] Rolled back {}", tool.label());
+    Ok(restored)
+}
+
+fn emit_result(app_handle: &AppHandle, tool: Tool, stage: InstallStage, result: &Result<Option<BinaryRecord>, String>) {
+    let (stage, version, error) = match result {
+        Ok(record) => (stage, record.as_ref().map(|r| r.version.clone()), None),
+        Err(e) => (InstallStage::Failed, None, Some(e.clone())),
+    };
+    let _ = app_handle.emit("binary-install-progress", BinaryInstallProgress {
+        tool,
+        stage,
+        downloaded_bytes: 0,
+        total_bytes: None,
+        version,
+        error,
+    });
+}
+
+// Tauri commands for managed binaries
+#[tauri::command]
+pub async fn get_binary_sources(state: State<'_, AppState>) -> Result<BinarySourceSettings, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    Ok(BinarySourceSettings::load(&db))
+}
+
+#[tauri::command]
+pub async fn set_binary_sources(state: State<'_, AppState>, settings: BinarySourceSettings) -> Result<(), String> {
+    settings.validate()?;
+    let serialized = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.save_setting(SOURCES_KEY, &serialized).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_binaries_status(app_handle: AppHandle, state: State<'_, AppState>) -> Result<Vec<BinaryStatus>, String> {
+    let dir = binaries_dir(&app_handle)?;
+    let (sources, mut installed) = {
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        (BinarySourceSettings::load(&db), load_installed(&db))
+    };
+
+    Ok(Tool::ALL
+        .into_iter()
+        .map(|tool| {
+            let path = dir.join(tool.file_name());
+            let versions = installed.remove(&tool).unwrap_or_default();
+            BinaryStatus {
+                tool,
+                path: path.exists().then(|| path.to_string_lossy().to_string()),
+                current: versions.current,
+                previous: versions.previous,
+                can_rollback: sibling(&dir, tool, "previous").exists(),
+                installable: sources.source_for(tool).is_some(),
+            }
+        })
+        .collect())
+}
+
+/// Installs or updates a tool in the app data `binaries` folder, reporting through
+/// `binary-install-progress` events
+#[tauri::command]
+pub async fn install_binary(app_handle: AppHandle, tool: Tool) -> Result<BinaryRecord, String> {
+    let mut result = install(&app_handle, tool).await;
+    if result.is_ok() {
+        // The binary is in place, but downloads can't use it until the downloader is rebuilt
+        if let Err(e) = Downloader::refresh(&app_handle).await {
+            result = Err(format!("{} was installed but could not be loaded: {}", tool.label(), e));
+        }
+    }
+    let reported = result.as_ref().map(|record| Some(record.clone())).map_err(|e| e.clone());
+    emit_result(&app_handle, tool, InstallStage::Completed, &reported);
+    result
+}
+
+/// Swaps the installed binary with the version it replaced
+#[tauri::command]
+pub async fn rollback_binary(app_handle: AppHandle, tool: Tool) -> Result<Option<BinaryRecord>, String> {
+    let mut result = rollback(&app_handle, tool);
+    if result.is_ok() {
+        if let Err(e) = Downloader::refresh(&app_handle).await {
+            result = Err(format!("{} was rolled back but could not be loaded: {}", tool.label(), e));
+        }
+    }
+    emit_result(&app_handle, tool, InstallStage::RolledBack, &result);
+    result
+}
@@ -0,0 +1,340 @@
+use crate::commands::AppState;
+use crate::database::{Database, Download};
+use crate::error::DownloadError;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+// Settings key the duplicate policy is persisted under
+const DUPLICATES_KEY: &str = "duplicates";
+
+// Query parameters that only track where a link was shared from
+const TRACKING_PARAMS: [&str; 12] = [
+    "fbclid", "gclid", "igshid", "igsh", "si", "feature", "ref", "ref_src", "ref_url", "share_id", "mc_cid", "mc_eid",
+];
+
+/// What to do when a URL points at media that was already downloaded
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicatePolicy {
+    Allow,
+    /// Queue it anyway, but emit a `duplicate-download` event
+    #[default]
+    Warn,
+    Skip,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct DuplicateSettings {
+    pub policy: DuplicatePolicy,
+}
+
+impl DuplicateSettings {
+    pub fn load(db: &Database) -> Self {
+        db.get_setting(DUPLICATES_KEY)
+            .ok()
+            .flatten()
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Payload of the duplicate-download event
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DuplicateWarning {
+    /// The download being added
+    pub id: String,
+    pub url: String,
+    pub canonical_id: String,
+    /// The completed download with the same canonical id
+    pub existing: Download,
+}
+
+/// A URL split into the parts canonicalization looks at
+struct ParsedUrl<'a> {
+    /// Lowercased, without "www." or "m."
+    host: String,
+    port: Option<&'a str>,
+    segments: Vec<&'a str>,
+    query: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> ParsedUrl<'a> {
+    fn parse(url: &'a str) -> Self {
+        let url = url.trim();
+        let url = url.split('#').next().unwrap_or(url);
+        let rest = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+        let (rest, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+
+        let host_port = authority.rsplit('@').next().unwrap_or(authority);
+        let (host, port) = match host_port.rsplit_once(':') {
+            Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => {
+                (host, Some(port).filter(|port| !port.is_empty()))
+            }
+            _ => (host_port, None),
+        };
+        let host = host.to_lowercase();
+        let host = host
+            .strip_prefix("www.")
+            .or_else(|| host.strip_prefix("m."))
+            .unwrap_or(&host)
+            .to_string();
+
+        Self {
+            host,
+            port,
+            segments: path.split('/').filter(|segment| !segment.is_empty()).collect(),
+            query: query
+                .split('&')
+                .filter(|pair| !pair.is_empty())
+                .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+                .collect(),
+        }
+    }
+
+    fn param(&self, name: &str) -> Option<&'a str> {
+        self.query
+            .iter()
+            .find(|(key, value)| *key == name && !value.is_empty())
+            .map(|(_, value)| *value)
+    }
+
+    fn segment(&self, index: usize) -> Option<&'a str> {
+        self.segments.get(index).copied()
+    }
+
+    /// Whether the host is `domain` or one of its subdomains
+    fn on(&self, domain: &str) -> bool {
+        self.host == domain || self.host.ends_with(&format!(".{}", domain))
+    }
+}
+
+/// The id of the media a URL points at on sites we know, e.g. "youtube:dQw4w9WgXcQ".
+///
+/// Site names match yt-dlp's lowercased `extractor_key`, so ids agree with [`from_info`].
+fn site_id(url: &ParsedUrl) -> Option<String> {
+    let id = |site: &str, id: &str| Some(format!("{}:{}", site, id));
+
+    if url.host == "youtu.be" {
+        return id("youtube", url.segment(0)?);
+    }
+    if url.on("youtube.com") || url.on("youtube-nocookie.com") {
+        return match url.segment(0)? {
+            "watch" => id("youtube", url.param("v")?),
+            "shorts" | "embed" | "live" | "v" => id("youtube", url.segment(1)?),
+            _ => None,
+        };
+    }
+    if url.on("vimeo.com") {
+        // vimeo.com/123456 and player.vimeo.com/video/123456
+        return url
+            .segments
+            .iter()
+            .find(|segment| segment.chars().all(|c| c.is_ascii_digit()))
+            .and_then(|video| id("vimeo", video));
+    }
+    if url.on("twitter.com") || url.on("x.com") {
+        // /<user>/status/<id>, optionally followed by /photo/1 or /video/1
+        let status = url.segments.iter().position(|segment| *segment == "status")?;
+        return id("twitter", url.segment(status + 1)?);
+    }
+    if url.on("tiktok.com") {
+        let video = url.segments.iter().position(|segment| *segment == "video")?;
+        return id("tiktok", url.segment(video + 1)?);
+    }
+    if url.on("instagram.com") {
+        return match url.segment(0)? {
+            "p" | "reel" | "reels" | "tv" => id("instagram", url.segment(1)?),
+            _ => None,
+        };
+    }
+    if url.host == "dai.ly" {
+        return id("dailymotion", url.segment(0)?);
+    }
+    if url.on("dailymotion.com") {
+        // /video/x8abc12_some-title keeps the id before the underscore
+        let video = url.segments.iter().position(|segment| *segment == "video")?;
+        let slug = url.segment(video + 1)?;
+        return id("dailymotion", slug.split('_').next().unwrap_or(slug));
+    }
+    None
+}
+
+/// The URL with tracking parameters, fragment, "www." and trailing slash removed
+fn clean_url(url: &str, parsed: &ParsedUrl) -> String {
+    let scheme = url
+        .trim()
+        .split_once("://")
+        .map(|(scheme, _)| scheme.to_lowercase())
+        .unwrap_or_else(|| "https".to_string());
+    let query: Vec<String> = parsed
+        .query
+        .iter()
+        .filter(|(key, _)| !key.starts_with("utm_") && !TRACKING_PARAMS.contains(key))
+        .map(|(key, value)| if value.is_empty() { key.to_string() } else { format!("{}={}", key, value) })
+        .collect();
+
+    let mut clean = format!("{}://{}", scheme, parsed.host);
+    if let Some(port) = parsed.port {
+        clean.push(':');
+        clean.push_str(port);
+    }
+    for segment in &parsed.segments {
+        clean.push('/');
+        clean.push_str(segment);
+    }
+    if !query.is_empty() {
+        clean.push('?');
+        clean.push_str(&query.join("&"));
+    }
+    clean
+}
+
+/// [`clean_url`] on its own; also the media info cache's key, so both agree on which
+/// URLs are the same
+pub fn normalize_url(url: &str) -> String {
+    clean_url(url, &ParsedUrl::parse(url))
+}
+
+/// "extractor:id" from yt-dlp's info for sites without a rule here
+pub fn from_info(extractor_key: Option<&str>, id: Option<&str>) -> Option<String> {
+    let extractor = extractor_key?.trim();
+    let id = id?.trim();
+    if extractor.is_empty() || id.is_empty() {
+        return None;
+    }
+    Some(format!("{}:{}", extractor.to_lowercase(), id))
+}
+
+/// The site id for `url`, if a rule here recognizes it
+pub fn known_id(url: &str) -> Option<String> {
+    site_id(&ParsedUrl::parse(url))
+}
+
+/// Canonical id from the URL alone: a site id, or the cleaned URL
+pub fn canonical_id(url: &str) -> String {
+    let parsed = ParsedUrl::parse(url);
+    site_id(&parsed).unwrap_or_else(|| clean_url(url, &parsed))
+}
+
+/// Canonical id for `url`, using cached yt-dlp info for sites without a rule here
+pub fn resolve(db: &Database, url: &str) -> String {
+    let parsed = ParsedUrl::parse(url);
+    site_id(&parsed)
+        .or_else(|| {
+            let info = crate::media_cache::lookup_in(db, url)?;
+            from_info(info["extractor_key"].as_str(), info["id"].as_str())
+        })
+        .unwrap_or_else(|| clean_url(url, &parsed))
+}
+
+/// Applies the duplicate policy to a download about to be added.
+///
+/// Returns the completed download it duplicates when the policy is to warn, and an
+/// error when it is to skip.
+pub fn check_duplicate(db: &Database, id: &str, url: &str, canonical_id: &str) -> Result<Option<Download>, DownloadError> {
+    let policy = DuplicateSettings::load(db).policy;
+    if policy == DuplicatePolicy::Allow {
+        return Ok(None);
+    }
+    let Some(existing) = db
+        .find_completed_download(canonical_id, url, id)
+        .map_err(|e| e.to_string())?
+    else {
+        return Ok(None);
+    };
+
+    println!("[Duplicates] {} was already downloaded as {}", url, existing.id);
+    if policy == DuplicatePolicy::Skip {
+        return Err(DownloadError::Duplicate {
+            message: format!("'{}' was already downloaded", existing.title),
+            existing_id: existing.id,
+        });
+    }
+    Ok(Some(existing))
+}
+
+pub fn emit_warning(app: &AppHandle, id: &str, url: &str, canonical_id: &str, existing: Download) {
+    let _ = app.emit("duplicate-download", DuplicateWarning {
+        id: id.to_string(),
+        url: url.to_string(),
+        canonical_id: canonical_id.to_string(),
+        existing,
+    });
+}
+
+// Tauri commands for duplicate detection
+#[tauri::command]
+pub async fn get_duplicate_settings(state: State<'_, AppState>) -> Result<DuplicateSettings, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    Ok(DuplicateSettings::load(&db))
+}
+
+#[tauri::command]
+pub async fn set_duplicate_settings(state: State<'_, AppState>, settings: DuplicateSettings) -> Result<(), String> {
+    let serialized = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.save_setting(DUPLICATES_KEY, &serialized).map_err(|e| e.to_string())
+}
+
+/// The completed download `url` duplicates, if any, regardless of the policy
+#[tauri::command]
+pub async fn find_duplicate_download(state: State<'_, AppState>, url: String) -> Result<Option<Download>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let canonical_id = resolve(&db, &url);
+    db.find_completed_download(&canonical_id, &url, "").map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn site_rules_find_the_media_id() {
+        let cases = [
+            ("https://youtu.be/dQw4w9WgXcQ", "youtube:dQw4w9WgXcQ"),
+            ("https://youtu.be/dQw4w9WgXcQ?si=abc123", "youtube:dQw4w9WgXcQ"),
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=5", "youtube:dQw4w9WgXcQ"),
+            ("https://m.youtube.com/watch?feature=share&v=dQw4w9WgXcQ", "youtube:dQw4w9WgXcQ"),
+            ("https://youtube.com/shorts/abcDEF12345", "youtube:abcDEF12345"),
+            ("https://vimeo.com/123456", "vimeo:123456"),
+            ("https://player.vimeo.com/video/123456?h=ff", "vimeo:123456"),
+            ("https://twitter.com/user/status/1700000000000000000", "twitter:1700000000000000000"),
+            ("https://x.com/user/status/1700000000000000000/video/1", "twitter:1700000000000000000"),
+            ("https://www.tiktok.com/@user/video/7200000000000000000", "tiktok:7200000000000000000"),
+            ("https://www.instagram.com/reel/Cabc123/", "instagram:Cabc123"),
+            ("https://www.dailymotion.com/video/x8abc12_some-title", "dailymotion:x8abc12"),
+            ("https://dai.ly/x8abc12", "dailymotion:x8abc12"),
+        ];
+        for (url, expected) in cases {
+            assert_eq!(canonical_id(url), expected, "{}", url);
+        }
+    }
+
+    #[test]
+    fn unknown_sites_fall_back_to_the_cleaned_url() {
+        let cases = [
+            (
+                "https://example.com/video/42?utm_source=news&utm_medium=email&quality=hd",
+                "https://example.com/video/42?quality=hd",
+            ),
+            ("https://www.example.com/video/42/?fbclid=abc#comments", "https://example.com/video/42"),
+            ("HTTPS://Example.COM/Video/42", "https://example.com/Video/42"),
+            ("http://localhost:8080/stream", "http://localhost:8080/stream"),
+            ("https://youtube.com/@channel", "https://youtube.com/@channel"),
+        ];
+        for (url, expected) in cases {
+            assert_eq!(canonical_id(url), expected, "{}", url);
+        }
+    }
+
+    #[test]
+    fn tracking_parameters_do_not_change_the_normalized_url() {
+        assert_eq!(
+            normalize_url("https://example.com/a?id=1&utm_campaign=x&gclid=y"),
+            normalize_url("https://www.example.com/a/?id=1"),
+        );
+        assert_ne!(normalize_url("https://example.com/a?id=1"), normalize_url("https://example.com/a?id=2"));
+    }
+}
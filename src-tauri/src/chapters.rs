@@ -0,0 +1,145 @@
+use crate::commands::AppState;
+use crate::database::{Database, DbResult, Download};
+use crate::downloader::DownloadRequest;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::State;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Chapter {
+    pub title: String,
+    /// Offsets from the start of the media, in seconds
+    pub start_time: f64,
+    pub end_time: f64,
+}
+
+/// One file written by `--split-chapters`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ChapterFile {
+    /// 1-based chapter number
+    pub index: usize,
+    pub title: String,
+    pub path: String,
+    pub size_bytes: Option<i64>,
+}
+
+/// Reads the chapter list from yt-dlp JSON, either the full info or a printed `chapters` field
+pub fn chapters_from_json(chapters: &serde_json::Value) -> Vec<Chapter> {
+    chapters
+        .as_array()
+        .map(|arr| {
+            arr.iter()
+                .enumerate()
+                .filter_map(|(index, chapter)| {
+                    Some(Chapter {
+                        title: chapter["title"]
+                            .as_str()
+                            .map(|s| s.to_string())
+                            .unwrap_or_else(|| format!("Chapter {}", index + 1)),
+                        start_time: chapter["start_time"].as_f64()?,
+                        end_time: chapter["end_time"].as_f64()?,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Folder the per-chapter files of a download are written to, next to the full file
+pub fn split_dir(stem: &Path) -> PathBuf {
+    let name = stem.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    stem.with_file_name(format!("{} - chapters", name))
+}
+
+/// yt-dlp arguments for chapter embedding and splitting
+pub fn args(request: &DownloadRequest, stem: &Path) -> Vec<String> {
+    let mut args = Vec::new();
+    if request.embed_chapters {
+        args.push("--embed-chapters".to_string());
+    }
+    if request.split_chapters {
+        let dir = crate::naming::escape_template(&split_dir(stem));
+        args.extend([
+            "--split-chapters".to_string(),
+            "-o".to_string(),
+            format!("chapter:{}/%(section_number)03d - %(section_title)s.%(ext)s", dir),
+        ]);
+    }
+    args
+}
+
+/// Matches the files in the split folder to chapters by their number prefix
+pub fn collect_files(stem: &Path, chapters: &[Chapter]) -> Vec<ChapterFile> {
+    let Ok(entries) = std::fs::read_dir(split_dir(stem)) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<ChapterFile> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter_map(|path| {
+            let name = path.file_name()?.to_string_lossy().to_string();
+            let index: usize = name.split(" - ").next()?.parse().ok()?;
+            let title = chapters
+                .get(index.checked_sub(1)?)
+                .map(|chapter| chapter.title.clone())
+                .unwrap_or_else(|| format!("Chapter {}", index));
+            Some(ChapterFile {
+                index,
+                title,
+                size_bytes: std::fs::metadata(&path).ok().map(|m| m.len() as i64),
+                path: path.to_string_lossy().to_string(),
+            })
+        })
+        .collect();
+    files.sort_by_key(|file| file.index);
+    files
+}
+
+/// Registers every chapter file as its own completed download linked to the parent,
+/// replacing the ones from an earlier run
+pub fn record(db: &Database, parent: &DownloadRequest, files: &[ChapterFile]) -> DbResult<()> {
+    // The history row carries the metadata yt-dlp reported during the download
+    let row = db.get_download(&parent.id)?;
+    let parent_title = row
+        .as_ref()
+        .map(|download| download.title.clone())
+        .or_else(|| parent.title.clone())
+        .unwrap_or_else(|| parent.url.clone());
+    let platform = row.as_ref().and_then(|d| d.platform.clone()).or_else(|| parent.platform.clone());
+    let thumbnail = row.as_ref().and_then(|d| d.thumbnail.clone()).or_else(|| parent.thumbnail.clone());
+    let canonical_id = row.as_ref().and_then(|d| d.canonical_id.clone());
+    db.delete_child_downloads(&parent.id)?;
+
+    let timestamp = chrono::Utc::now().timestamp_millis();
+    for file in files {
+        db.add_download(&Download {
+            id: Uuid::new_v4().to_string(),
+            title: format!("{} - {:03} {}", parent_title, file.index, file.title),
+            url: parent.url.clone(),
+            format: "chapter".to_string(),
+            path: file.path.clone(),
+            timestamp,
+            status: "completed".to_string(),
+            size_bytes: file.size_bytes,
+            platform: platform.clone(),
+            thumbnail: thumbnail.clone(),
+            parent_id: Some(parent.id.clone()),
+            clip: None,
+            canonical_id: canonical_id.clone(),
+        })?;
+    }
+    Ok(())
+}
+
+// Tauri commands for chapters
+#[tauri::command]
+pub async fn get_download_chapters(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<Vec<Download>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_child_downloads(&id).map_err(|e| e.to_string())
+}
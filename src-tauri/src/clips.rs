@@ -0,0 +1,251 @@
+use crate::database::{Database, DbResult, Download};
+use crate::downloader::DownloadRequest;
+use crate::error::DownloadError;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::path::Path;
+use uuid::Uuid;
+
+/// A section of the media to download, in seconds from the start
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct ClipRange {
+    /// Seconds, or a "HH:MM:SS(.ms)" / "MM:SS" timestamp when sent from the frontend
+    #[serde(deserialize_with = "deserialize_timestamp")]
+    pub start: f64,
+    /// Open-ended ranges run to the end of the media
+    #[serde(default, deserialize_with = "deserialize_optional_timestamp")]
+    pub end: Option<f64>,
+}
+
+/// One section file written for a request with several ranges
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ClipFile {
+    pub range: ClipRange,
+    pub path: String,
+    pub size_bytes: Option<i64>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Timestamp {
+    Seconds(f64),
+    Text(String),
+}
+
+impl Timestamp {
+    fn seconds<E: serde::de::Error>(self) -> Result<f64, E> {
+        match self {
+            Timestamp::Seconds(seconds) => Ok(seconds),
+            Timestamp::Text(text) => parse_timestamp(&text)
+                .ok_or_else(|| E::custom(format!("invalid timestamp '{}'", text))),
+        }
+    }
+}
+
+fn deserialize_timestamp<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    Timestamp::deserialize(deserializer)?.seconds()
+}
+
+fn deserialize_optional_timestamp<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f64>, D::Error> {
+    Option::<Timestamp>::deserialize(deserializer)?
+        .map(Timestamp::seconds)
+        .transpose()
+}
+
+/// Parses "SS", "MM:SS" or "HH:MM:SS", each optionally with fractional seconds
+pub fn parse_timestamp(value: &str) -> Option<f64> {
+    let parts: Vec<&str> = value.trim().split(':').collect();
+    if parts.is_empty() || parts.len() > 3 {
+        return None;
+    }
+    let mut seconds = 0.0;
+    for part in parts {
+        let number: f64 = part.trim().parse().ok()?;
+        if !number.is_finite() || number < 0.0 {
+            return None;
+        }
+        seconds = seconds * 60.0 + number;
+    }
+    Some(seconds)
+}
+
+/// Formats seconds as "HH:MM:SS", keeping milliseconds only when present
+pub fn format_timestamp(seconds: f64) -> String {
+    let millis = (seconds * 1000.0).round() as i64;
+    let (hours, rest) = (millis / 3_600_000, millis % 3_600_000);
+    let (minutes, rest) = (rest / 60_000, rest % 60_000);
+    let (secs, millis) = (rest / 1000, rest % 1000);
+    if millis == 0 {
+        format!("{:02}:{:02}:{:02}", hours, minutes, secs)
+    } else {
+        format!("{:02}:{:02}:{:02}.{:03}", hours, minutes, secs, millis)
+    }
+}
+
+impl ClipRange {
+    /// Human readable form for the history row, e.g. "00:10:00–00:12:30"
+    pub fn label(&self) -> String {
+        match self.end {
+            Some(end) => format!("{}–{}", format_timestamp(self.start), format_timestamp(end)),
+            None => format!("{}–end", format_timestamp(self.start)),
+        }
+    }
+
+    /// Value for `--download-sections`
+    fn as_arg(&self) -> String {
+        match self.end {
+            Some(end) => format!("*{}-{}", self.start, end),
+            None => format!("*{}-inf", self.start),
+        }
+    }
+}
+
+/// Label stored in the history row, or `None` for a full download
+pub fn label(ranges: &[ClipRange]) -> Option<String> {
+    if ranges.is_empty() {
+        return None;
+    }
+    Some(ranges.iter().map(ClipRange::label).collect::<Vec<_>>().join(", "))
+}
+
+/// Checks that ranges are well-formed and do not overlap
+pub fn validate(request: &DownloadRequest) -> Result<(), DownloadError> {
+    let mut ranges = request.clip_ranges.clone();
+    ranges.sort_by(|a, b| a.start.total_cmp(&b.start));
+    // Both would record their files as child rows of the same download
+    if ranges.len() > 1 && request.split_chapters {
+        return Err(DownloadError::invalid("Splitting by chapter is not supported with several clips"));
+    }
+
+    for range in &ranges {
+        if !range.start.is_finite() || range.start < 0.0 {
+            return Err(DownloadError::invalid("Clip start must be a non-negative time"));
+        }
+        if let Some(end) = range.end {
+            if !end.is_finite() || end <= range.start {
+                return Err(DownloadError::invalid(format!("Clip {} ends before it starts", range.label())));
+            }
+        }
+    }
+
+    for pair in ranges.windows(2) {
+        if pair[0].end.is_none_or(|end| end > pair[1].start) {
+            return Err(DownloadError::invalid(format!(
+                "Clips {} and {} overlap",
+                pair[0].label(),
+                pair[1].label()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Checks the ranges against the media length reported by `get_media_info`
+pub fn validate_duration(ranges: &[ClipRange], duration: Option<i64>) -> Result<(), DownloadError> {
+    let Some(duration) = duration else {
+        // Live streams and some sites don't report a duration
+        return Ok(());
+    };
+    let duration = duration as f64;
+    if let Some(range) = ranges
+        .iter()
+        .find(|range| range.start >= duration || range.end.is_some_and(|end| end > duration + 1.0))
+    {
+        return Err(DownloadError::invalid(format!(
+            "Clip {} is outside the media, which is {} long",
+            range.label(),
+            format_timestamp(duration)
+        )));
+    }
+    Ok(())
+}
+
+/// yt-dlp arguments for section downloading
+pub fn args(ranges: &[ClipRange]) -> Vec<String> {
+    ranges
+        .iter()
+        .flat_map(|range| ["--download-sections".to_string(), range.as_arg()])
+        .collect()
+}
+
+/// Output template for the request; with several ranges each section gets its own file
+pub fn output_template(ranges: &[ClipRange], stem: &Path) -> String {
+    if ranges.len() > 1 {
+        format!(
+            "{} (%(section_start)s-%(section_end)s).%(ext)s",
+            crate::naming::escape_template(stem)
+        )
+    } else {
+        crate::naming::output_template(stem)
+    }
+}
+
+/// Start of the section a file was written for, from the " (start-end)" that
+/// [`output_template`] puts before the extension
+fn section_start(path: &str) -> Option<f64> {
+    let stem = Path::new(path).file_stem()?.to_string_lossy().to_string();
+    let (_, section) = stem.strip_suffix(')')?.rsplit_once(" (")?;
+    section.split_once('-')?.0.parse().ok()
+}
+
+/// Pairs the files yt-dlp reported after moving them with the ranges they were cut from.
+///
+/// A single range writes to the download's own path, so there is nothing to pair.
+pub fn collect_files(ranges: &[ClipRange], paths: &[String]) -> Vec<ClipFile> {
+    if ranges.len() < 2 {
+        return Vec::new();
+    }
+    let mut sorted = ranges.to_vec();
+    sorted.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+    paths
+        .iter()
+        .enumerate()
+        .filter_map(|(index, path)| {
+            // yt-dlp writes sections in order, which stands in if the name can't be read
+            let range = section_start(path)
+                .and_then(|start| sorted.iter().find(|range| (range.start - start).abs() < 1.0))
+                .or_else(|| sorted.get(index))?;
+            Some(ClipFile {
+                range: *range,
+                path: path.clone(),
+                size_bytes: std::fs::metadata(path).ok().map(|m| m.len() as i64),
+            })
+        })
+        .collect()
+}
+
+/// Registers every section file as its own completed download linked to the parent,
+/// replacing the ones from an earlier run
+pub fn record(db: &Database, parent: &DownloadRequest, files: &[ClipFile]) -> DbResult<()> {
+    let row = db.get_download(&parent.id)?;
+    let parent_title = row
+        .as_ref()
+        .map(|download| download.title.clone())
+        .or_else(|| parent.title.clone())
+        .unwrap_or_else(|| parent.url.clone());
+    let platform = row.as_ref().and_then(|d| d.platform.clone()).or_else(|| parent.platform.clone());
+    let thumbnail = row.as_ref().and_then(|d| d.thumbnail.clone()).or_else(|| parent.thumbnail.clone());
+    let canonical_id = row.as_ref().and_then(|d| d.canonical_id.clone());
+    db.delete_child_downloads(&parent.id)?;
+
+    let timestamp = chrono::Utc::now().timestamp_millis();
+    for file in files {
+        let label = file.range.label();
+        db.add_download(&Download {
+            id: Uuid::new_v4().to_string(),
+            title: format!("{} - clip {}", parent_title, label),
+            url: parent.url.clone(),
+            format: "clip".to_string(),
+            path: file.path.clone(),
+            timestamp,
+            status: "completed".to_string(),
+            size_bytes: file.size_bytes,
+            platform: platform.clone(),
+            thumbnail: thumbnail.clone(),
+            parent_id: Some(parent.id.clone()),
+            clip: Some(label),
+            canonical_id: canonical_id.clone(),
+        })?;
+    }
+    Ok(())
+}
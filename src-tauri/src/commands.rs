@@ -1,5 +1,5 @@
 use crate::database::{Database, Download, SearchHistory, Setting};
-use tauri::{AppHandle, Manager, State};
+use tauri::{AppHandle, State};
 use std::sync::Mutex;
 
 pub struct AppState {
@@ -7,13 +7,31 @@ pub struct AppState {
 }
 
 // Download commands
+/// Adds a history row, applying the duplicate policy unless it is a clip or chapter file
 #[tauri::command]
 pub async fn add_download(
+    app_handle: AppHandle,
     state: State<'_, AppState>,
-    download: Download,
+    mut download: Download,
 ) -> Result<(), String> {
     let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.add_download(&download).map_err(|e| e.to_string())
+    let canonical_id = match download.canonical_id.take() {
+        Some(canonical_id) => canonical_id,
+        None => crate::canonical::resolve(&db, &download.url),
+    };
+    let duplicate = if download.clip.is_none() && download.parent_id.is_none() {
+        crate::canonical::check_duplicate(&db, &download.id, &download.url, &canonical_id)
+            .map_err(|e| e.to_string())?
+    } else {
+        None
+    };
+
+    download.canonical_id = Some(canonical_id.clone());
+    db.add_download(&download).map_err(|e| e.to_string())?;
+    if let Some(existing) = duplicate {
+        crate::canonical::emit_warning(&app_handle, &download.id, &download.url, &canonical_id, existing);
+    }
+    Ok(())
 }
 
 #[tauri::command]
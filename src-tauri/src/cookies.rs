@@ -0,0 +1,309 @@
+use crate::downloader::Downloader;
+use crate::error::DownloadError;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+const NETSCAPE_HEADER: &str = "# Netscape HTTP Cookie File";
+// yt-dlp prefixes HttpOnly cookies with this instead of commenting them out
+const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+const BROWSERS: [&str; 9] = [
+    "brave", "chrome", "chromium", "edge", "firefox", "opera", "safari", "vivaldi", "whale",
+];
+// Jars whose last cookie expires within this window are reported as expiring
+const EXPIRING_SOON_SECS: i64 = 7 * 24 * 60 * 60;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CookieJarStatus {
+    Valid,
+    ExpiringSoon,
+    Expired,
+}
+
+/// A stored cookie file for one site, with its expiry report
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CookieJar {
+    /// e.g. "youtube.com"; also used for its subdomains
+    pub domain: String,
+    pub path: String,
+    pub cookie_count: usize,
+    /// Cookies without an expiry, which only last for the browser session
+    pub session_count: usize,
+    pub expired_count: usize,
+    /// When the last persistent cookie expires, in milliseconds
+    pub expires_at: Option<i64>,
+    pub status: CookieJarStatus,
+    /// When the jar was imported or last refreshed by yt-dlp, in milliseconds
+    pub updated_at: Option<i64>,
+}
+
+/// One line of a Netscape cookie file; only the fields we need are kept
+struct CookieLine {
+    domain: String,
+    /// Seconds since the epoch; 0 for session cookies
+    expires: i64,
+    raw: String,
+}
+
+fn parse_cookies(contents: &str) -> Result<Vec<CookieLine>, String> {
+    let mut cookies = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        let entry = line.strip_prefix(HTTP_ONLY_PREFIX).unwrap_or(line);
+        if entry.trim().is_empty() || (entry.starts_with('#') && !line.starts_with(HTTP_ONLY_PREFIX)) {
+            continue;
+        }
+
+        let fields: Vec<&str> = entry.split('\t').collect();
+        if fields.len() != 7 {
+            return Err(format!(
+                "Line {} is not a Netscape cookie entry (expected 7 tab-separated fields)",
+                number + 1
+            ));
+        }
+        let expires = fields[4]
+            .trim()
+            .parse::<f64>()
+            .map_err(|_| format!("Line {} has an invalid expiry", number + 1))?;
+        cookies.push(CookieLine {
+            domain: fields[0].trim().trim_start_matches('.').to_lowercase(),
+            expires: expires as i64,
+            raw: line.to_string(),
+        });
+    }
+
+    if cookies.is_empty() {
+        return Err("The file does not contain any cookies".to_string());
+    }
+    Ok(cookies)
+}
+
+/// Whether `host` is `domain` or one of its subdomains
+fn domain_matches(host: &str, domain: &str) -> bool {
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+/// Turns user input such as "https://www.YouTube.com/watch" into "youtube.com"
+fn normalize_domain(input: &str) -> Result<String, String> {
+    let host = crate::scheduler::url_host(input.trim());
+    let host = host.trim_start_matches('.');
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    let valid = host.contains('.')
+        && !host.starts_with('.')
+        && !host.ends_with('.')
+        && !host.contains("..")
+        && host.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+    if !valid {
+        return Err(format!("'{}' is not a valid domain", input));
+    }
+    Ok(host.to_string())
+}
+
+/// The domain most of the cookies belong to, for imports that don't name one
+fn guess_domain(cookies: &[CookieLine]) -> Option<String> {
+    let mut counts: std::collections::BTreeMap<String, usize> = std::collections::BTreeMap::new();
+    for cookie in cookies {
+        if let Ok(domain) = normalize_domain(&cookie.domain) {
+            *counts.entry(domain).or_default() += 1;
+        }
+    }
+    counts.into_iter().max_by_key(|(_, count)| *count).map(|(domain, _)| domain)
+}
+
+pub fn cookies_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    let app_data_dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(app_data_dir.join("cookies"))
+}
+
+fn jar_path(dir: &Path, domain: &str) -> PathBuf {
+    dir.join(format!("{}.txt", domain))
+}
+
+/// Picks the stored jar for a URL, preferring the most specific domain
+pub fn jar_for_url(dir: &Path, url: &str) -> Option<PathBuf> {
+    let host = crate::scheduler::url_host(url);
+    // Short links are served from a different domain than the site's cookies
+    let host = if host == "youtu.be" { "youtube.com".to_string() } else { host };
+
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .filter_map(|path| {
+            let domain = path.file_stem()?.to_string_lossy().to_string();
+            domain_matches(&host, &domain).then_some((domain.len(), path))
+        })
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, path)| path)
+}
+
+/// Keeps the cookies for `domain` and stores them as its jar, replacing any earlier one
+fn save_jar(dir: &Path, domain: &str, cookies: &[CookieLine]) -> Result<CookieJar, String> {
+    let lines: Vec<&str> = cookies
+        .iter()
+        .filter(|cookie| domain_matches(&cookie.domain, domain))
+        .map(|cookie| cookie.raw.as_str())
+        .collect();
+    if lines.is_empty() {
+        return Err(format!("No cookies for {} were found", domain));
+    }
+
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let path = jar_path(dir, domain);
+    let contents = format!("{}\n\n{}\n", NETSCAPE_HEADER, lines.join("\n"));
+    std::fs::write(&path, contents).map_err(|e| e.to_string())?;
+    // Cookies are credentials; keep them private to the current user
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).map_err(|e| e.to_string())?;
+    }
+
+    println!("[Cookies] Stored {} cookies for {}", lines.len(), domain);
+    read_jar(&path)
+}
+
+fn read_jar(path: &Path) -> Result<CookieJar, String> {
+    let domain = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let cookies = parse_cookies(&contents)?;
+
+    let now = chrono::Utc::now().timestamp();
+    let session_count = cookies.iter().filter(|cookie| cookie.expires == 0).count();
+    let expired_count = cookies
+        .iter()
+        .filter(|cookie| cookie.expires != 0 && cookie.expires <= now)
+        .count();
+    let last_expiry = cookies.iter().map(|cookie| cookie.expires).filter(|&expires| expires != 0).max();
+
+    let status = match last_expiry {
+        _ if session_count == 0 && expired_count == cookies.len() => CookieJarStatus::Expired,
+        Some(expires) if expires > now && expires - now <= EXPIRING_SOON_SECS => CookieJarStatus::ExpiringSoon,
+        _ => CookieJarStatus::Valid,
+    };
+    let updated_at = std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .map(|modified| chrono::DateTime::<chrono::Utc>::from(modified).timestamp_millis());
+
+    Ok(CookieJar {
+        domain,
+        path: path.to_string_lossy().to_string(),
+        cookie_count: cookies.len(),
+        session_count,
+        expired_count,
+        expires_at: last_expiry.map(|expires| expires * 1000),
+        status,
+        updated_at,
+    })
+}
+
+impl Downloader {
+    /// Has yt-dlp read the browser's cookie store and write it out as a Netscape file
+    async fn export_browser_cookies(&self, browser: &str, destination: &Path) -> Result<(), DownloadError> {
+        let output = self
+            .yt_dlp_command(None)?
+            .args([
+                "--cookies-from-browser",
+                browser,
+                "--cookies",
+                &destination.to_string_lossy(),
+            ])
+            .output()
+            .await
+            .map_err(|e| DownloadError::from_spawn("yt-dlp", e))?;
+
+        // Without a URL yt-dlp exits with a usage error, but only after saving the cookie file
+        if !destination.exists() {
+            return Err(DownloadError::from_stderr(&String::from_utf8_lossy(&output.stderr)));
+        }
+        Ok(())
+    }
+}
+
+// Tauri commands for cookie files
+#[tauri::command]
+pub async fn list_cookie_jars(app_handle: AppHandle) -> Result<Vec<CookieJar>, String> {
+    let dir = cookies_dir(&app_handle)?;
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+
+    let mut jars: Vec<CookieJar> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .filter_map(|path| match read_jar(&path) {
+            Ok(jar) => Some(jar),
+            Err(e) => {
+                println!("[Cookies] Skipping unreadable jar {:?}: {}", path, e);
+                None
+            }
+        })
+        .collect();
+    jars.sort_by(|a, b| a.domain.cmp(&b.domain));
+    Ok(jars)
+}
+
+/// Imports a Netscape-format cookie file; without a domain, the one most cookies belong to is used
+#[tauri::command]
+pub async fn import_cookie_file(
+    app_handle: AppHandle,
+    path: String,
+    domain: Option<String>,
+) -> Result<CookieJar, String> {
+    let contents = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let cookies = parse_cookies(&contents)?;
+    let domain = match domain {
+        Some(domain) => normalize_domain(&domain)?,
+        None => guess_domain(&cookies).ok_or_else(|| "Could not tell which site the cookies are for".to_string())?,
+    };
+    save_jar(&cookies_dir(&app_handle)?, &domain, &cookies)
+}
+
+/// Copies a site's cookies out of an installed browser's profile
+#[tauri::command]
+pub async fn import_browser_cookies(
+    app_handle: AppHandle,
+    browser: String,
+    domain: String,
+) -> Result<CookieJar, DownloadError> {
+    let browser = browser.trim().to_lowercase();
+    if !BROWSERS.contains(&browser.as_str()) {
+        return Err(DownloadError::invalid(format!(
+            "Unsupported browser '{}'. Use one of: {}",
+            browser,
+            BROWSERS.join(", ")
+        )));
+    }
+    let domain = normalize_domain(&domain).map_err(DownloadError::invalid)?;
+
+    let dir = cookies_dir(&app_handle)?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    // The export holds every site's cookies, so it never stays on disk
+    let export = dir.join(format!(".{}-export.tmp", browser));
+    let result = Downloader::shared(&app_handle)
+        .export_browser_cookies(&browser, &export)
+        .await
+        .and_then(|_| {
+            let contents = std::fs::read_to_string(&export).map_err(|e| e.to_string())?;
+            let cookies = parse_cookies(&contents)?;
+            Ok(save_jar(&dir, &domain, &cookies)?)
+        });
+    let _ = std::fs::remove_file(&export);
+    result
+}
+
+#[tauri::command]
+pub async fn delete_cookie_jar(app_handle: AppHandle, domain: String) -> Result<(), String> {
+    let domain = normalize_domain(&domain)?;
+    let path = jar_path(&cookies_dir(&app_handle)?, &domain);
+    std::fs::remove_file(&path).map_err(|e| format!("Failed to delete cookies for {}: {}", domain, e))?;
+    println!("[Cookies] Deleted cookies for {}", domain);
+    Ok(())
+}
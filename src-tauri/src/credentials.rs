@@ -0,0 +1,429 @@
+use crate::commands::AppState;
+use crate::database::{CredentialVault, Database, EncryptedCredential};
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const MIN_PASSPHRASE_LEN: usize = 8;
+// Encrypted on setup so a wrong passphrase can be told apart from a corrupt credential
+const VERIFIER: &[u8] = b"omnidownloader-credentials-v1";
+const REDACTED: &str = "********";
+
+/// Holds the key derived from the passphrase while the store is unlocked.
+///
+/// The key only ever lives in memory; locking or restarting the app forgets it.
+pub struct CredentialStore {
+    key: Mutex<Option<[u8; KEY_LEN]>>,
+}
+
+impl CredentialStore {
+    pub fn new() -> Self {
+        Self { key: Mutex::new(None) }
+    }
+
+    fn key(&self) -> Result<[u8; KEY_LEN], String> {
+        self.key
+            .lock()
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "The credential store is locked".to_string())
+    }
+
+    fn set_key(&self, key: Option<[u8; KEY_LEN]>) -> Result<(), String> {
+        *self.key.lock().map_err(|e| e.to_string())? = key;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CredentialStoreStatus {
+    /// Whether a passphrase has been set up
+    pub initialized: bool,
+    pub unlocked: bool,
+}
+
+/// What the frontend sees of a stored login; the password never leaves the backend
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CredentialSummary {
+    pub machine: String,
+    pub login: String,
+    pub updated_at: i64,
+}
+
+/// The encrypted part of a credential
+#[derive(Serialize, Deserialize)]
+struct Secret {
+    login: String,
+    password: String,
+}
+
+/// A decrypted login for one extractor
+#[derive(Clone)]
+pub struct Credential {
+    /// yt-dlp's netrc machine name for the extractor, e.g. "youtube" or "vimeo"
+    pub machine: String,
+    pub login: String,
+    /// Password or API token
+    pub password: String,
+    pub updated_at: i64,
+}
+
+impl std::fmt::Debug for Credential {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credential")
+            .field("machine", &self.machine)
+            .field("login", &self.login)
+            .field("password", &REDACTED)
+            .finish()
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; KEY_LEN], String> {
+    let mut key = [0u8; KEY_LEN];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive key: {}", e))?;
+    Ok(key)
+}
+
+fn encrypt(key: &[u8; KEY_LEN], plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| "Failed to encrypt credential".to_string())?;
+    Ok((nonce.to_vec(), ciphertext))
+}
+
+fn decrypt(key: &[u8; KEY_LEN], nonce: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>> {
+    if nonce.len() != NONCE_LEN {
+        return None;
+    }
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .ok()
+}
+
+/// Sets up a new vault for `passphrase`, returning it with its key
+fn new_vault(passphrase: &str) -> Result<(CredentialVault, [u8; KEY_LEN]), String> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(format!("Passphrase must be at least {} characters", MIN_PASSPHRASE_LEN));
+    }
+    let mut salt = vec![0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let key = derive_key(passphrase, &salt)?;
+    let (nonce, verifier) = encrypt(&key, VERIFIER)?;
+    Ok((CredentialVault { salt, nonce, verifier }, key))
+}
+
+fn open_vault(vault: &CredentialVault, passphrase: &str) -> Result<[u8; KEY_LEN], String> {
+    let key = derive_key(passphrase, &vault.salt)?;
+    match decrypt(&key, &vault.nonce, &vault.verifier) {
+        Some(plaintext) if plaintext == VERIFIER => Ok(key),
+        _ => Err("Incorrect passphrase".to_string()),
+    }
+}
+
+fn encrypt_credential(key: &[u8; KEY_LEN], credential: &Credential) -> Result<EncryptedCredential, String> {
+    let secret = Secret {
+        login: credential.login.clone(),
+        password: credential.password.clone(),
+    };
+    let plaintext = serde_json::to_vec(&secret).map_err(|e| e.to_string())?;
+    let (nonce, secret) = encrypt(key, &plaintext)?;
+    Ok(EncryptedCredential {
+        machine: credential.machine.clone(),
+        nonce,
+        secret,
+        updated_at: credential.updated_at,
+    })
+}
+
+fn decrypt_credentials(db: &Database, key: &[u8; KEY_LEN]) -> Result<Vec<Credential>, String> {
+    db.get_credentials()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|stored| {
+            let secret = decrypt(key, &stored.nonce, &stored.secret)
+                .and_then(|plaintext| serde_json::from_slice::<Secret>(&plaintext).ok())
+                .ok_or_else(|| format!("Stored credential for {} could not be decrypted", stored.machine))?;
+            Ok(Credential {
+                machine: stored.machine,
+                login: secret.login,
+                password: secret.password,
+                updated_at: stored.updated_at,
+            })
+        })
+        .collect()
+}
+
+fn validate_credential(machine: &str, login: &str, password: &str) -> Result<(), String> {
+    let valid_machine = !machine.is_empty()
+        && machine
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "._-".contains(c));
+    if !valid_machine {
+        return Err(format!(
+            "Invalid extractor name '{}': use lowercase letters, digits, '.', '_' or '-'",
+            machine
+        ));
+    }
+    if login.trim().is_empty() || password.is_empty() {
+        return Err("Both a login and a password or token are required".to_string());
+    }
+    if login.contains(['\r', '\n']) || password.contains(['\r', '\n']) {
+        return Err("Logins and passwords must be a single line".to_string());
+    }
+    Ok(())
+}
+
+/// Quotes a netrc token when it contains characters the netrc lexer would split on
+fn netrc_token(value: &str) -> String {
+    if value.chars().any(|c| c.is_whitespace() || c == '"' || c == '\\') {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn netrc_dir(app_handle: &AppHandle) -> Option<PathBuf> {
+    app_handle.path().app_data_dir().ok().map(|dir| dir.join("netrc"))
+}
+
+/// Removes netrc files left behind if the app exited while yt-dlp was running
+pub fn clear_temp_netrc(app_handle: &AppHandle) {
+    if let Some(dir) = netrc_dir(app_handle) {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
+
+/// The unlocked credentials, ready to be handed to yt-dlp
+pub struct Netrc {
+    dir: PathBuf,
+    credentials: Vec<Credential>,
+}
+
+impl Netrc {
+    /// `None` while the store is locked or empty
+    pub fn load(app_handle: &AppHandle) -> Option<Self> {
+        let key = app_handle.try_state::<CredentialStore>()?.key().ok()?;
+        let state = app_handle.try_state::<AppState>()?;
+        let credentials = {
+            let db = state.db.lock().ok()?;
+            decrypt_credentials(&db, &key).ok()?
+        };
+        if credentials.is_empty() {
+            return None;
+        }
+        Some(Self {
+            dir: netrc_dir(app_handle)?,
+            credentials,
+        })
+    }
+
+    /// "machine login" for each credential, without passwords
+    pub fn accounts(&self) -> Vec<String> {
+        self.credentials
+            .iter()
+            .map(|credential| format!("{} {}", credential.machine, credential.login))
+            .collect()
+    }
+
+    /// Writes a netrc readable only by the current user for a single yt-dlp invocation
+    pub fn write(self) -> Result<TempNetrc, String> {
+        std::fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        let path = self.dir.join(format!("{}.netrc", uuid::Uuid::new_v4()));
+        let contents: String = self
+            .credentials
+            .iter()
+            .map(|credential| {
+                format!(
+                    "machine {} login {} password {}\n",
+                    credential.machine,
+                    netrc_token(&credential.login),
+                    netrc_token(&credential.password)
+                )
+            })
+            .collect();
+
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let netrc = TempNetrc {
+            path,
+            secrets: self.credentials.into_iter().map(|credential| credential.password).collect(),
+        };
+        std::io::Write::write_all(
+            &mut options.open(&netrc.path).map_err(|e| e.to_string())?,
+            contents.as_bytes(),
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(netrc)
+    }
+}
+
+/// A netrc file that is deleted once the yt-dlp call using it is done
+pub struct TempNetrc {
+    path: PathBuf,
+    /// Passwords and tokens written to the file, to be masked in yt-dlp output
+    secrets: Vec<String>,
+}
+
+impl TempNetrc {
+    pub fn args(&self) -> Vec<String> {
+        vec![
+            "--netrc".to_string(),
+            "--netrc-location".to_string(),
+            self.path.to_string_lossy().to_string(),
+        ]
+    }
+}
+
+/// Masks every password or token in the netrc in yt-dlp output before it is logged or emitted
+pub fn redact(netrc: Option<&TempNetrc>, text: &str) -> String {
+    netrc
+        .map(|netrc| netrc.secrets.iter().filter(|secret| !secret.is_empty()))
+        .into_iter()
+        .flatten()
+        .fold(text.to_string(), |text, secret| text.replace(secret.as_str(), REDACTED))
+}
+
+impl Drop for TempNetrc {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+// Tauri commands for the credential store
+#[tauri::command]
+pub async fn get_credential_store_status(
+    state: State<'_, AppState>,
+    store: State<'_, CredentialStore>,
+) -> Result<CredentialStoreStatus, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    Ok(CredentialStoreStatus {
+        initialized: db.get_credential_vault().map_err(|e| e.to_string())?.is_some(),
+        unlocked: store.key().is_ok(),
+    })
+}
+
+/// Unlocks the store, setting it up with this passphrase the first time
+#[tauri::command]
+pub async fn unlock_credential_store(
+    state: State<'_, AppState>,
+    store: State<'_, CredentialStore>,
+    passphrase: String,
+) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let key = match db.get_credential_vault().map_err(|e| e.to_string())? {
+        Some(vault) => open_vault(&vault, &passphrase)?,
+        None => {
+            let (vault, key) = new_vault(&passphrase)?;
+            db.replace_credential_vault(Some(&vault), &[]).map_err(|e| e.to_string())?;
+            println!("[Credentials] Created credential store");
+            key
+        }
+    };
+    store.set_key(Some(key))
+}
+
+#[tauri::command]
+pub async fn lock_credential_store(store: State<'_, CredentialStore>) -> Result<(), String> {
+    store.set_key(None)
+}
+
+/// Re-encrypts every stored credential under a new passphrase
+#[tauri::command]
+pub async fn change_credential_passphrase(
+    state: State<'_, AppState>,
+    store: State<'_, CredentialStore>,
+    current: String,
+    new: String,
+) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let vault = db
+        .get_credential_vault()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "The credential store has not been set up".to_string())?;
+    let old_key = open_vault(&vault, &current)?;
+    let credentials = decrypt_credentials(&db, &old_key)?;
+
+    let (vault, key) = new_vault(&new)?;
+    let encrypted = credentials
+        .iter()
+        .map(|credential| encrypt_credential(&key, credential))
+        .collect::<Result<Vec<_>, _>>()?;
+    db.replace_credential_vault(Some(&vault), &encrypted).map_err(|e| e.to_string())?;
+    store.set_key(Some(key))
+}
+
+/// Deletes the passphrase and every stored credential, e.g. when the passphrase is forgotten
+#[tauri::command]
+pub async fn reset_credential_store(
+    state: State<'_, AppState>,
+    store: State<'_, CredentialStore>,
+) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.replace_credential_vault(None, &[]).map_err(|e| e.to_string())?;
+    println!("[Credentials] Credential store reset");
+    store.set_key(None)
+}
+
+#[tauri::command]
+pub async fn list_credentials(
+    state: State<'_, AppState>,
+    store: State<'_, CredentialStore>,
+) -> Result<Vec<CredentialSummary>, String> {
+    let key = store.key()?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    Ok(decrypt_credentials(&db, &key)?
+        .into_iter()
+        .map(|credential| CredentialSummary {
+            machine: credential.machine,
+            login: credential.login,
+            updated_at: credential.updated_at,
+        })
+        .collect())
+}
+
+/// Stores or replaces the login for an extractor
+#[tauri::command]
+pub async fn save_credential(
+    state: State<'_, AppState>,
+    store: State<'_, CredentialStore>,
+    machine: String,
+    login: String,
+    password: String,
+) -> Result<(), String> {
+    let machine = machine.trim().to_lowercase();
+    validate_credential(&machine, &login, &password)?;
+    let key = store.key()?;
+    let credential = Credential {
+        machine,
+        login: login.trim().to_string(),
+        password,
+        updated_at: chrono::Utc::now().timestamp_millis(),
+    };
+    let encrypted = encrypt_credential(&key, &credential)?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.save_credential(&encrypted).map_err(|e| e.to_string())?;
+    println!("[Credentials] Saved login for {}", credential.machine);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_credential(state: State<'_, AppState>, machine: String) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.delete_credential(machine.trim()).map_err(|e| e.to_string())
+}
@@ -1,7 +1,7 @@
-use rusqlite::{Connection, Result, params};
+use rusqlite::{Connection, OptionalExtension, Result, params};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use chrono::{DateTime, Utc};
+use chrono::Utc;
 use std::path::PathBuf;
 use thiserror::Error;
 
@@ -11,6 +11,8 @@ pub enum DatabaseError {
     Sqlite(#[from] rusqlite::Error),
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
+    #[error("Migration error: {0}")]
+    Migration(String),
 }
 
 pub type DbResult<T> = std::result::Result<T, DatabaseError>;
@@ -27,6 +29,13 @@ pub struct Download {
     pub size_bytes: Option<i64>,
     pub platform: Option<String>,
     pub thumbnail: Option<String>,
+    /// Set on per-chapter files split from another download
+    pub parent_id: Option<String>,
+    /// Downloaded time ranges, e.g. "00:10:00–00:12:30"; `None` for the full media
+    pub clip: Option<String>,
+    /// Identity of the media independent of how the URL was written, e.g. "youtube:dQw4w9WgXcQ"
+    #[serde(default)]
+    pub canonical_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -44,6 +53,116 @@ pub struct Setting {
     pub value: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DownloadAttempt {
+    pub id: i64,
+    pub download_id: String,
+    pub attempt: i64,
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+    pub outcome: Option<String>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DownloadSubtitle {
+    pub id: i64,
+    pub download_id: String,
+    pub language: String,
+    pub format: String,
+    pub path: Option<String>,
+    pub embedded: bool,
+    pub created_at: i64,
+}
+
+/// Key derivation parameters and the passphrase check for the credential store
+#[derive(Debug, Clone)]
+pub struct CredentialVault {
+    pub salt: Vec<u8>,
+    pub nonce: Vec<u8>,
+    pub verifier: Vec<u8>,
+}
+
+/// A stored login; `secret` is the encrypted login and password
+#[derive(Debug, Clone)]
+pub struct EncryptedCredential {
+    pub machine: String,
+    pub nonce: Vec<u8>,
+    pub secret: Vec<u8>,
+    pub updated_at: i64,
+}
+
+/// How imported rows are merged with rows that have the same id (or settings key)
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    SkipExisting,
+    Overwrite,
+    /// Replace only rows with an older timestamp. Settings carry no timestamp, so
+    /// they fall back to [`MergeStrategy::SkipExisting`]
+    NewestWins,
+}
+
+impl MergeStrategy {
+    /// The rule settings are merged by under this strategy
+    pub fn for_settings(self) -> MergeStrategy {
+        match self {
+            MergeStrategy::NewestWins => MergeStrategy::SkipExisting,
+            strategy => strategy,
+        }
+    }
+
+    fn replaces(self, existing_timestamp: Option<i64>, incoming_timestamp: Option<i64>) -> bool {
+        match self {
+            MergeStrategy::SkipExisting => false,
+            MergeStrategy::Overwrite => true,
+            MergeStrategy::NewestWins => matches!(
+                (existing_timestamp, incoming_timestamp),
+                (Some(existing), Some(incoming)) if incoming > existing
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ImportCounts {
+    pub inserted: usize,
+    pub updated: usize,
+    pub skipped: usize,
+}
+
+impl ImportCounts {
+    fn record(&mut self, exists: bool, replace: bool) {
+        match (exists, replace) {
+            (false, _) => self.inserted += 1,
+            (true, true) => self.updated += 1,
+            (true, false) => self.skipped += 1,
+        }
+    }
+}
+
+/// A cached yt-dlp info extraction; the JSON itself is only read when it is reused
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MediaCacheEntry {
+    pub url: String,
+    pub title: Option<String>,
+    pub fetched_at: i64,
+    /// Filled in from the current cache lifetime, which is a setting rather than stored per entry
+    pub expires_at: i64,
+    pub size_bytes: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QueueEntry {
+    pub id: String,
+    pub request: String,
+    pub state: String,
+    pub attempts: i64,
+    pub last_error: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
 pub struct Database {
     conn: Connection,
 }
@@ -56,74 +175,40 @@ impl Database {
         let db_path = app_data_dir.join("db.sqlite");
         let conn = Connection::open(&db_path)?;
         
-        let db = Self { conn };
-        db.initialize_tables()?;
-        
-        Ok(db)
-    }
-
-    fn initialize_tables(&self) -> DbResult<()> {
-        self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS downloads (
-                id TEXT PRIMARY KEY,
-                title TEXT NOT NULL,
-                url TEXT NOT NULL,
-                format TEXT NOT NULL,
-                path TEXT NOT NULL,
-                timestamp INTEGER NOT NULL,
-                status TEXT NOT NULL,
-                size_bytes INTEGER,
-                platform TEXT,
-                thumbnail TEXT
-            )",
-            [],
-        )?;
-
-        self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS search_history (
-                id TEXT PRIMARY KEY,
-                query TEXT NOT NULL,
-                timestamp INTEGER NOT NULL,
-                title TEXT,
-                thumbnail TEXT
-            )",
-            [],
-        )?;
+        crate::migrations::run(&conn, &db_path)?;
 
-        self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS settings (
-                key TEXT PRIMARY KEY,
-                value TEXT NOT NULL
-            )",
-            [],
-        )?;
-
-        // Create indexes for faster queries
-        self.conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_downloads_timestamp ON downloads(timestamp DESC)",
-            [],
-        )?;
+        Ok(Self { conn })
+    }
 
+    // Download operations
+    pub fn add_download(&self, download: &Download) -> DbResult<()> {
         self.conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_search_history_timestamp ON search_history(timestamp DESC)",
-            [],
+            "INSERT INTO downloads (id, title, url, format, path, timestamp, status, size_bytes, platform, thumbnail, parent_id, clip, canonical_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                download.id,
+                download.title,
+                download.url,
+                download.format,
+                download.path,
+                download.timestamp,
+                download.status,
+                download.size_bytes,
+                download.platform,
+                download.thumbnail,
+                download.parent_id,
+                download.clip,
+                download.canonical_id,
+            ],
         )?;
-
-        // Migration: Add thumbnail column to downloads if it doesn't exist
-        let _ = self.conn.execute("ALTER TABLE downloads ADD COLUMN thumbnail TEXT", []);
-        
-        // Migration: Add title and thumbnail columns to search_history if they don't exist
-        let _ = self.conn.execute("ALTER TABLE search_history ADD COLUMN title TEXT", []);
-        let _ = self.conn.execute("ALTER TABLE search_history ADD COLUMN thumbnail TEXT", []);
-
         Ok(())
     }
 
-    // Download operations
-    pub fn add_download(&self, download: &Download) -> DbResult<()> {
+    /// Inserts a history row, leaving an existing row with the same id untouched
+    pub fn insert_download_if_missing(&self, download: &Download) -> DbResult<()> {
         self.conn.execute(
-            "INSERT INTO downloads (id, title, url, format, path, timestamp, status, size_bytes, platform, thumbnail)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            "INSERT OR IGNORE INTO downloads (id, title, url, format, path, timestamp, status, size_bytes, platform, thumbnail, parent_id, clip, canonical_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
             params![
                 download.id,
                 download.title,
@@ -135,6 +220,9 @@ impl Database {
                 download.size_bytes,
                 download.platform,
                 download.thumbnail,
+                download.parent_id,
+                download.clip,
+                download.canonical_id,
             ],
         )?;
         Ok(())
@@ -142,29 +230,127 @@ impl Database {
 
     pub fn get_downloads(&self) -> DbResult<Vec<Download>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, title, url, format, path, timestamp, status, size_bytes, platform, thumbnail
+            "SELECT id, title, url, format, path, timestamp, status, size_bytes, platform, thumbnail, parent_id, clip, canonical_id
              FROM downloads ORDER BY timestamp DESC"
         )?;
 
-        let downloads = stmt.query_map([], |row| {
-            Ok(Download {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                url: row.get(2)?,
-                format: row.get(3)?,
-                path: row.get(4)?,
-                timestamp: row.get(5)?,
-                status: row.get(6)?,
-                size_bytes: row.get(7)?,
-                platform: row.get(8)?,
-                thumbnail: row.get(9)?,
-            })
-        })?
-        .collect::<Result<Vec<_>, _>>()?;
+        let downloads = stmt.query_map([], Self::map_download)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(downloads)
+    }
+
+    pub fn get_download(&self, id: &str) -> DbResult<Option<Download>> {
+        let result = self.conn.query_row(
+            "SELECT id, title, url, format, path, timestamp, status, size_bytes, platform, thumbnail, parent_id, clip, canonical_id
+             FROM downloads WHERE id = ?1",
+            params![id],
+            Self::map_download,
+        );
+
+        match result {
+            Ok(download) => Ok(Some(download)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Per-chapter downloads split from a parent download, in chapter order
+    pub fn get_child_downloads(&self, parent_id: &str) -> DbResult<Vec<Download>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, title, url, format, path, timestamp, status, size_bytes, platform, thumbnail, parent_id, clip, canonical_id
+             FROM downloads WHERE parent_id = ?1 ORDER BY path ASC"
+        )?;
+
+        let downloads = stmt.query_map(params![parent_id], Self::map_download)?
+            .collect::<Result<Vec<_>, _>>()?;
 
         Ok(downloads)
     }
 
+    pub fn delete_child_downloads(&self, parent_id: &str) -> DbResult<()> {
+        self.conn.execute("DELETE FROM downloads WHERE parent_id = ?1", params![parent_id])?;
+        Ok(())
+    }
+
+    fn map_download(row: &rusqlite::Row) -> rusqlite::Result<Download> {
+        Ok(Download {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            url: row.get(2)?,
+            format: row.get(3)?,
+            path: row.get(4)?,
+            timestamp: row.get(5)?,
+            status: row.get(6)?,
+            size_bytes: row.get(7)?,
+            platform: row.get(8)?,
+            thumbnail: row.get(9)?,
+            parent_id: row.get(10)?,
+            clip: row.get(11)?,
+            canonical_id: row.get(12)?,
+        })
+    }
+
+    /// Canonical ids of completed full downloads, matching what `find_completed_download`
+    /// treats as a duplicate; failed, cancelled, clip and chapter rows don't count
+    pub fn get_completed_canonical_ids(&self) -> DbResult<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT canonical_id FROM downloads
+             WHERE canonical_id IS NOT NULL AND status = 'completed' AND parent_id IS NULL AND clip IS NULL"
+        )?;
+        let ids = stmt.query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ids)
+    }
+
+    /// The newest completed full download of the same media, other than `exclude_id`;
+    /// rows without a canonical id are matched on the exact URL
+    pub fn find_completed_download(&self, canonical_id: &str, url: &str, exclude_id: &str) -> DbResult<Option<Download>> {
+        let result = self.conn.query_row(
+            "SELECT id, title, url, format, path, timestamp, status, size_bytes, platform, thumbnail, parent_id, clip, canonical_id
+             FROM downloads
+             WHERE (canonical_id = ?1 OR (canonical_id IS NULL AND url = ?2))
+               AND status = 'completed' AND parent_id IS NULL AND clip IS NULL AND id != ?3
+             ORDER BY timestamp DESC LIMIT 1",
+            params![canonical_id, url, exclude_id],
+            Self::map_download,
+        );
+
+        match result {
+            Ok(download) => Ok(Some(download)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn update_download_canonical_id(&self, id: &str, canonical_id: &str) -> DbResult<()> {
+        self.conn.execute(
+            "UPDATE downloads SET canonical_id = ?1 WHERE id = ?2",
+            params![canonical_id, id],
+        )?;
+        Ok(())
+    }
+
+    /// Computes canonical ids for rows that don't have one yet; returns how many were filled in
+    pub fn backfill_canonical_ids(&self, canonicalize: impl Fn(&str) -> String) -> DbResult<usize> {
+        let rows: Vec<(String, String)> = {
+            let mut stmt = self.conn.prepare("SELECT id, url FROM downloads WHERE canonical_id IS NULL")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows
+        };
+
+        let tx = self.conn.unchecked_transaction()?;
+        for (id, url) in &rows {
+            tx.execute(
+                "UPDATE downloads SET canonical_id = ?1 WHERE id = ?2",
+                params![canonicalize(url), id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(rows.len())
+    }
+
     pub fn update_download_status(&self, id: &str, status: &str) -> DbResult<()> {
         self.conn.execute(
             "UPDATE downloads SET status = ?1 WHERE id = ?2",
@@ -173,13 +359,47 @@ impl Database {
         Ok(())
     }
 
+    /// Stores the final file path and size reported by yt-dlp, keeping the old path if none was reported
+    pub fn update_download_result(&self, id: &str, path: Option<&str>, size_bytes: Option<i64>) -> DbResult<()> {
+        self.conn.execute(
+            "UPDATE downloads SET path = COALESCE(?1, path), size_bytes = COALESCE(?2, size_bytes) WHERE id = ?3",
+            params![path, size_bytes, id],
+        )?;
+        Ok(())
+    }
+
+    /// Fills in metadata yt-dlp reported; the title only replaces the URL placeholder
+    pub fn update_download_metadata(
+        &self,
+        id: &str,
+        title: Option<&str>,
+        platform: Option<&str>,
+        thumbnail: Option<&str>,
+    ) -> DbResult<()> {
+        self.conn.execute(
+            "UPDATE downloads SET
+                title = CASE WHEN title = url AND ?1 IS NOT NULL THEN ?1 ELSE title END,
+                platform = COALESCE(?2, platform),
+                thumbnail = COALESCE(thumbnail, ?3)
+             WHERE id = ?4",
+            params![title, platform, thumbnail, id],
+        )?;
+        Ok(())
+    }
+
     pub fn delete_download(&self, id: &str) -> DbResult<()> {
-        self.conn.execute("DELETE FROM downloads WHERE id = ?1", params![id])?;
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM downloads WHERE id = ?1 OR parent_id = ?1", params![id])?;
+        tx.execute("DELETE FROM download_subtitles WHERE download_id = ?1", params![id])?;
+        tx.commit()?;
         Ok(())
     }
 
     pub fn clear_downloads(&self) -> DbResult<()> {
-        self.conn.execute("DELETE FROM downloads", [])?;
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM downloads", [])?;
+        tx.execute("DELETE FROM download_subtitles", [])?;
+        tx.commit()?;
         Ok(())
     }
 
@@ -215,6 +435,12 @@ impl Database {
         Ok(history)
     }
 
+    /// Every search, newest first
+    pub fn get_all_search_history(&self) -> DbResult<Vec<SearchHistory>> {
+        // SQLite treats a negative LIMIT as no limit
+        self.get_search_history(-1)
+    }
+
     pub fn clear_search_history(&self) -> DbResult<()> {
         self.conn.execute("DELETE FROM search_history", [])?;
         Ok(())
@@ -261,4 +487,416 @@ impl Database {
         self.conn.execute("DELETE FROM settings WHERE key = ?1", params![key])?;
         Ok(())
     }
+
+    // Download queue operations
+    /// Adds a job, or starts over one that has finished; returns false, changing
+    /// nothing, when a job with this id is still queued, running, paused or retrying
+    pub fn enqueue_job(&self, id: &str, request: &str) -> DbResult<bool> {
+        let now = Utc::now().timestamp_millis();
+        let changed = self.conn.execute(
+            "INSERT INTO download_queue (id, request, state, attempts, last_error, created_at, updated_at)
+             VALUES (?1, ?2, 'queued', 0, NULL, ?3, ?3)
+             ON CONFLICT(id) DO UPDATE SET request = ?2, state = 'queued', attempts = 0, last_error = NULL, updated_at = ?3
+             WHERE state IN ('completed', 'failed', 'cancelled')",
+            params![id, request, now],
+        )?;
+        Ok(changed > 0)
+    }
+
+    pub fn get_job(&self, id: &str) -> DbResult<Option<QueueEntry>> {
+        let result = self.conn.query_row(
+            "SELECT id, request, state, attempts, last_error, created_at, updated_at
+             FROM download_queue WHERE id = ?1",
+            params![id],
+            Self::map_queue_entry,
+        );
+
+        match result {
+            Ok(entry) => Ok(Some(entry)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn get_queue(&self) -> DbResult<Vec<QueueEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, request, state, attempts, last_error, created_at, updated_at
+             FROM download_queue ORDER BY created_at ASC"
+        )?;
+
+        let entries = stmt.query_map([], Self::map_queue_entry)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(entries)
+    }
+
+    /// Jobs that were queued, running or waiting to retry when the app last exited
+    pub fn get_unfinished_jobs(&self) -> DbResult<Vec<QueueEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, request, state, attempts, last_error, created_at, updated_at
+             FROM download_queue WHERE state IN ('queued', 'running', 'retrying') ORDER BY created_at ASC"
+        )?;
+
+        let entries = stmt.query_map([], Self::map_queue_entry)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(entries)
+    }
+
+    pub fn update_job_request(&self, id: &str, request: &str) -> DbResult<()> {
+        self.conn.execute(
+            "UPDATE download_queue SET request = ?1, updated_at = ?2 WHERE id = ?3",
+            params![request, Utc::now().timestamp_millis(), id],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_job_state(&self, id: &str, state: &str, last_error: Option<&str>) -> DbResult<()> {
+        self.conn.execute(
+            "UPDATE download_queue SET state = ?1, last_error = ?2, updated_at = ?3 WHERE id = ?4",
+            params![state, last_error, Utc::now().timestamp_millis(), id],
+        )?;
+        Ok(())
+    }
+
+    /// Bumps the attempt counter and returns the new value
+    pub fn increment_job_attempts(&self, id: &str) -> DbResult<i64> {
+        self.conn.execute(
+            "UPDATE download_queue SET attempts = attempts + 1, updated_at = ?1 WHERE id = ?2",
+            params![Utc::now().timestamp_millis(), id],
+        )?;
+        let attempts = self.conn.query_row(
+            "SELECT attempts FROM download_queue WHERE id = ?1",
+            params![id],
+            |row| row.get(0),
+        )?;
+        Ok(attempts)
+    }
+
+    // Download attempt operations
+    pub fn start_attempt(&self, download_id: &str, attempt: i64) -> DbResult<i64> {
+        self.conn.execute(
+            "INSERT INTO download_attempts (download_id, attempt, started_at) VALUES (?1, ?2, ?3)",
+            params![download_id, attempt, Utc::now().timestamp_millis()],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// The attempt left open by a paused, relaunched or interrupted run, if any
+    pub fn get_open_attempt(&self, download_id: &str) -> DbResult<Option<i64>> {
+        Ok(self.conn.query_row(
+            "SELECT id FROM download_attempts WHERE download_id = ?1 AND finished_at IS NULL
+             ORDER BY id DESC LIMIT 1",
+            params![download_id],
+            |row| row.get(0),
+        ).optional()?)
+    }
+
+    pub fn finish_attempt(&self, attempt_id: i64, outcome: &str, reason: Option<&str>) -> DbResult<()> {
+        self.conn.execute(
+            "UPDATE download_attempts SET finished_at = ?1, outcome = ?2, reason = ?3 WHERE id = ?4",
+            params![Utc::now().timestamp_millis(), outcome, reason, attempt_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_download_attempts(&self, download_id: &str) -> DbResult<Vec<DownloadAttempt>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, download_id, attempt, started_at, finished_at, outcome, reason
+             FROM download_attempts WHERE download_id = ?1 ORDER BY id ASC"
+        )?;
+
+        let attempts = stmt.query_map(params![download_id], |row| {
+            Ok(DownloadAttempt {
+                id: row.get(0)?,
+                download_id: row.get(1)?,
+                attempt: row.get(2)?,
+                started_at: row.get(3)?,
+                finished_at: row.get(4)?,
+                outcome: row.get(5)?,
+                reason: row.get(6)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(attempts)
+    }
+
+    // Subtitle operations
+    pub fn add_download_subtitle(
+        &self,
+        download_id: &str,
+        language: &str,
+        format: &str,
+        path: Option<&str>,
+        embedded: bool,
+    ) -> DbResult<()> {
+        self.conn.execute(
+            "INSERT INTO download_subtitles (download_id, language, format, path, embedded, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![download_id, language, format, path, embedded, Utc::now().timestamp_millis()],
+        )?;
+        Ok(())
+    }
+
+    pub fn clear_download_subtitles(&self, download_id: &str) -> DbResult<()> {
+        self.conn.execute("DELETE FROM download_subtitles WHERE download_id = ?1", params![download_id])?;
+        Ok(())
+    }
+
+    pub fn get_download_subtitles(&self, download_id: &str) -> DbResult<Vec<DownloadSubtitle>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, download_id, language, format, path, embedded, created_at
+             FROM download_subtitles WHERE download_id = ?1 ORDER BY language ASC"
+        )?;
+
+        let subtitles = stmt.query_map(params![download_id], |row| {
+            Ok(DownloadSubtitle {
+                id: row.get(0)?,
+                download_id: row.get(1)?,
+                language: row.get(2)?,
+                format: row.get(3)?,
+                path: row.get(4)?,
+                embedded: row.get(5)?,
+                created_at: row.get(6)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(subtitles)
+    }
+
+    // Credential store operations
+    pub fn get_credential_vault(&self) -> DbResult<Option<CredentialVault>> {
+        let mut stmt = self.conn.prepare("SELECT salt, nonce, verifier FROM credential_vault WHERE id = 1")?;
+        let mut rows = stmt.query([])?;
+
+        if let Some(row) = rows.next()? {
+            Ok(Some(CredentialVault {
+                salt: row.get(0)?,
+                nonce: row.get(1)?,
+                verifier: row.get(2)?,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn get_credentials(&self) -> DbResult<Vec<EncryptedCredential>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT machine, nonce, secret, updated_at FROM credentials ORDER BY machine ASC"
+        )?;
+
+        let credentials = stmt.query_map([], |row| {
+            Ok(EncryptedCredential {
+                machine: row.get(0)?,
+                nonce: row.get(1)?,
+                secret: row.get(2)?,
+                updated_at: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(credentials)
+    }
+
+    pub fn save_credential(&self, credential: &EncryptedCredential) -> DbResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO credentials (machine, nonce, secret, updated_at) VALUES (?1, ?2, ?3, ?4)",
+            params![credential.machine, credential.nonce, credential.secret, credential.updated_at],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_credential(&self, machine: &str) -> DbResult<()> {
+        self.conn.execute("DELETE FROM credentials WHERE machine = ?1", params![machine])?;
+        Ok(())
+    }
+
+    /// Replaces the vault and every credential at once, e.g. after a passphrase change;
+    /// `None` removes the store entirely
+    pub fn replace_credential_vault(
+        &self,
+        vault: Option<&CredentialVault>,
+        credentials: &[EncryptedCredential],
+    ) -> DbResult<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM credentials", [])?;
+        tx.execute("DELETE FROM credential_vault", [])?;
+        if let Some(vault) = vault {
+            tx.execute(
+                "INSERT INTO credential_vault (id, salt, nonce, verifier) VALUES (1, ?1, ?2, ?3)",
+                params![vault.salt, vault.nonce, vault.verifier],
+            )?;
+        }
+        for credential in credentials {
+            tx.execute(
+                "INSERT INTO credentials (machine, nonce, secret, updated_at) VALUES (?1, ?2, ?3, ?4)",
+                params![credential.machine, credential.nonce, credential.secret, credential.updated_at],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    // Media info cache operations
+    /// The newest fresh info for `url`, from the given context or, with `None`, from any
+    pub fn get_cached_media_info(&self, url: &str, context: Option<&str>, fresh_after: i64) -> DbResult<Option<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT info FROM media_info_cache
+             WHERE url = ?1 AND (?2 IS NULL OR context = ?2) AND fetched_at > ?3
+             ORDER BY fetched_at DESC LIMIT 1"
+        )?;
+        let mut rows = stmt.query(params![url, context, fresh_after])?;
+
+        if let Some(row) = rows.next()? {
+            Ok(Some(row.get(0)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn save_cached_media_info(&self, url: &str, context: &str, title: Option<&str>, info: &str) -> DbResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO media_info_cache (url, context, title, info, fetched_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![url, context, title, info, Utc::now().timestamp_millis()],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_media_cache_entries(&self) -> DbResult<Vec<MediaCacheEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT url, title, fetched_at, length(info) FROM media_info_cache ORDER BY fetched_at DESC"
+        )?;
+
+        let entries = stmt.query_map([], |row| {
+            Ok(MediaCacheEntry {
+                url: row.get(0)?,
+                title: row.get(1)?,
+                fetched_at: row.get(2)?,
+                expires_at: 0,
+                size_bytes: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(entries)
+    }
+
+    /// Removes entries fetched before `fresh_after`; returns how many were removed
+    pub fn prune_media_cache(&self, fresh_after: i64) -> DbResult<usize> {
+        Ok(self.conn.execute("DELETE FROM media_info_cache WHERE fetched_at <= ?1", params![fresh_after])?)
+    }
+
+    pub fn delete_cached_media_info(&self, url: &str) -> DbResult<usize> {
+        Ok(self.conn.execute("DELETE FROM media_info_cache WHERE url = ?1", params![url])?)
+    }
+
+    pub fn clear_media_cache(&self) -> DbResult<usize> {
+        Ok(self.conn.execute("DELETE FROM media_info_cache", [])?)
+    }
+
+    pub fn remove_job(&self, id: &str) -> DbResult<()> {
+        self.conn.execute("DELETE FROM download_queue WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    pub fn clear_finished_jobs(&self) -> DbResult<()> {
+        self.conn.execute(
+            "DELETE FROM download_queue WHERE state IN ('completed', 'failed', 'cancelled')",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Merges exported rows into the database in one transaction, so a failed import changes nothing.
+    ///
+    /// Returns the counts for downloads, search history and settings, in that order.
+    pub fn import_rows(
+        &self,
+        downloads: &[Download],
+        searches: &[SearchHistory],
+        settings: &[Setting],
+        strategy: MergeStrategy,
+    ) -> DbResult<(ImportCounts, ImportCounts, ImportCounts)> {
+        let tx = self.conn.unchecked_transaction()?;
+
+        let mut download_counts = ImportCounts::default();
+        for download in downloads {
+            let existing: Option<i64> = tx
+                .query_row("SELECT timestamp FROM downloads WHERE id = ?1", params![download.id], |row| row.get(0))
+                .optional()?;
+            let replace = strategy.replaces(existing, Some(download.timestamp));
+            if existing.is_none() || replace {
+                tx.execute(
+                    "INSERT OR REPLACE INTO downloads (id, title, url, format, path, timestamp, status, size_bytes, platform, thumbnail, parent_id, clip, canonical_id)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                    params![
+                        download.id,
+                        download.title,
+                        download.url,
+                        download.format,
+                        download.path,
+                        download.timestamp,
+                        download.status,
+                        download.size_bytes,
+                        download.platform,
+                        download.thumbnail,
+                        download.parent_id,
+                        download.clip,
+                        download.canonical_id,
+                    ],
+                )?;
+            }
+            download_counts.record(existing.is_some(), replace);
+        }
+
+        let mut search_counts = ImportCounts::default();
+        for search in searches {
+            let existing: Option<i64> = tx
+                .query_row("SELECT timestamp FROM search_history WHERE id = ?1", params![search.id], |row| row.get(0))
+                .optional()?;
+            let replace = strategy.replaces(existing, Some(search.timestamp));
+            if existing.is_none() || replace {
+                tx.execute(
+                    "INSERT OR REPLACE INTO search_history (id, query, timestamp, title, thumbnail) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![search.id, search.query, search.timestamp, search.title, search.thumbnail],
+                )?;
+            }
+            search_counts.record(existing.is_some(), replace);
+        }
+
+        let mut setting_counts = ImportCounts::default();
+        let setting_strategy = strategy.for_settings();
+        for setting in settings {
+            let exists: bool = tx.query_row(
+                "SELECT EXISTS (SELECT 1 FROM settings WHERE key = ?1)",
+                params![setting.key],
+                |row| row.get(0),
+            )?;
+            let replace = setting_strategy.replaces(None, None);
+            if !exists || replace {
+                tx.execute(
+                    "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
+                    params![setting.key, setting.value],
+                )?;
+            }
+            setting_counts.record(exists, replace);
+        }
+
+        tx.commit()?;
+        Ok((download_counts, search_counts, setting_counts))
+    }
+
+    fn map_queue_entry(row: &rusqlite::Row) -> rusqlite::Result<QueueEntry> {
+        Ok(QueueEntry {
+            id: row.get(0)?,
+            request: row.get(1)?,
+            state: row.get(2)?,
+            attempts: row.get(3)?,
+            last_error: row.get(4)?,
+            created_at: row.get(5)?,
+            updated_at: row.get(6)?,
+        })
+    }
 }
@@ -1,17 +1,85 @@
+use crate::audio::{AudioFormat, AudioQuality};
+use crate::binaries::Tool;
+use crate::chapters::{Chapter, ChapterFile};
+use crate::clips::{ClipFile, ClipRange};
+use crate::commands::AppState;
+use crate::credentials::{redact, Netrc, TempNetrc};
+use crate::error::DownloadError;
+use crate::media_cache::InfoJsonFile;
+use crate::naming::{CollisionPolicy, OutputPlan};
+use crate::network::NetworkSettings;
+use crate::subtitles::{SubtitleFile, SubtitleOptions, SubtitleTrack};
+use crate::toolchain::{ToolCandidate, ToolSource, ToolStatus};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::process::Stdio;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 use tauri::{AppHandle, Emitter, Manager};
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 
-// Track active download processes for cancellation
+// Prefixes for the lines we ask yt-dlp to print, so they can be told apart from progress
+const FILEPATH_MARKER: &str = "[OmniFilepath] ";
+const POSTPROCESS_MARKER: &str = "[OmniPostprocess] ";
+const INFO_MARKER: &str = "[OmniInfo] ";
+const SUBTITLES_MARKER: &str = "[OmniSubtitles] ";
+const CHAPTERS_MARKER: &str = "[OmniChapters] ";
+
+/// Why a running download is being stopped
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StopSignal {
+    Cancel,
+    // Keep the .part files so the download can continue later
+    Pause,
+    // Restart straight away, e.g. with a new bandwidth limit
+    Relaunch,
+}
+
+// Track active download processes for cancellation and pausing
 lazy_static::lazy_static! {
-    static ref ACTIVE_DOWNLOADS: Arc<Mutex<HashMap<String, tokio::sync::oneshot::Sender<()>>>> = 
+    static ref ACTIVE_DOWNLOADS: Arc<Mutex<HashMap<String, tokio::sync::oneshot::Sender<StopSignal>>>> = 
         Arc::new(Mutex::new(HashMap::new()));
 }
 
+/// Keeps a download's stop sender registered until its run returns, however it returns
+struct ActiveDownload {
+    id: String,
+}
+
+impl ActiveDownload {
+    fn register(id: &str) -> (Self, tokio::sync::oneshot::Receiver<StopSignal>) {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        ACTIVE_DOWNLOADS.lock().unwrap().insert(id.to_string(), tx);
+        (Self { id: id.to_string() }, rx)
+    }
+}
+
+impl Drop for ActiveDownload {
+    fn drop(&mut self) {
+        ACTIVE_DOWNLOADS.lock().unwrap().remove(&self.id);
+    }
+}
+
+impl StopSignal {
+    fn outcome(self) -> DownloadOutcome {
+        match self {
+            StopSignal::Cancel => DownloadOutcome::Cancelled,
+            StopSignal::Pause => DownloadOutcome::Paused,
+            StopSignal::Relaunch => DownloadOutcome::Relaunched,
+        }
+    }
+}
+
+/// A stop requested while the download was still being prepared
+fn stop_requested(cancel_rx: &mut tokio::sync::oneshot::Receiver<StopSignal>) -> Option<StopSignal> {
+    match cancel_rx.try_recv() {
+        Ok(signal) => Some(signal),
+        Err(tokio::sync::oneshot::error::TryRecvError::Empty) => None,
+        Err(tokio::sync::oneshot::error::TryRecvError::Closed) => Some(StopSignal::Cancel),
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DownloadProgress {
     pub id: String,
@@ -22,6 +90,7 @@ pub struct DownloadProgress {
     pub downloaded_bytes: Option<i64>,
     pub total_bytes: Option<i64>,
     pub filename: Option<String>,
+    pub error: Option<DownloadError>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -31,13 +100,52 @@ pub struct DownloadRequest {
     pub output_path: String,
     pub format: Option<String>,
     pub audio_only: bool,
+    // Extraction codec and quality for audio-only downloads; default to the saved settings
+    pub audio_format: Option<AudioFormat>,
+    pub audio_quality: Option<AudioQuality>,
     pub quality: Option<String>,
+    /// Bytes per second; overrides the global limit and its schedule
+    pub rate_limit: Option<u64>,
+    /// Overrides for the saved network settings
+    pub network: Option<NetworkSettings>,
+    /// Download only these sections instead of the whole media
+    #[serde(default)]
+    pub clip_ranges: Vec<ClipRange>,
+    pub subtitles: Option<SubtitleOptions>,
     pub embed_thumbnail: bool,
     pub embed_metadata: bool,
+    #[serde(default)]
+    pub embed_chapters: bool,
+    /// Also write one file per chapter, each recorded as a download linked to this one
+    #[serde(default)]
+    pub split_chapters: bool,
+    // Shown in download history until yt-dlp reports the real values
+    pub title: Option<String>,
+    pub thumbnail: Option<String>,
+    pub platform: Option<String>,
+    // Override the saved naming settings for this download
+    pub filename_template: Option<String>,
+    pub collision_policy: Option<CollisionPolicy>,
+    // Set for playlist entries so templates can use {playlist} and {playlist_index}
+    pub playlist_title: Option<String>,
+    pub playlist_index: Option<usize>,
+    /// File path relative to `output_path` without extension, fixed on the first run
+    pub output_name: Option<String>,
+}
+
+/// The metadata yt-dlp prints just before downloading
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MediaSummary {
+    pub id: Option<String>,
+    pub title: Option<String>,
+    pub extractor: Option<String>,
+    pub extractor_key: Option<String>,
+    pub thumbnail: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MediaInfo {
+    pub id: Option<String>,
     pub title: String,
     pub duration: Option<i64>,
     pub thumbnail: Option<String>,
@@ -49,6 +157,10 @@ pub struct MediaInfo {
     pub like_count: Option<i64>,
     pub upload_date: Option<String>,
     pub webpage_url: Option<String>,
+    /// Resolution of the best format, e.g. "1920x1080"
+    pub resolution: Option<String>,
+    pub subtitles: Vec<SubtitleTrack>,
+    pub chapters: Vec<Chapter>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -66,22 +178,82 @@ pub struct FormatInfo {
     pub quality_label: Option<String>,
 }
 
+/// Where a finished download ended up on disk
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CompletedDownload {
+    pub filepath: Option<String>,
+    pub size_bytes: Option<i64>,
+    pub subtitles: Vec<SubtitleFile>,
+    pub chapters: Vec<ChapterFile>,
+    /// One file per range when several clip ranges were requested
+    pub clips: Vec<ClipFile>,
+}
+
+/// How a single yt-dlp run ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum DownloadOutcome {
+    Completed(CompletedDownload),
+    Failed(DownloadError),
+    Cancelled,
+    Paused,
+    /// Stopped by the scheduler to be started again with different options
+    Relaunched,
+}
+
+impl DownloadOutcome {
+    pub fn status(&self) -> &'static str {
+        match self {
+            DownloadOutcome::Completed(_) => "completed",
+            DownloadOutcome::Failed(_) => "failed",
+            DownloadOutcome::Cancelled => "cancelled",
+            DownloadOutcome::Paused => "paused",
+            DownloadOutcome::Relaunched => "relaunched",
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct YtDlpInfo {
     pub version: String,
     pub path: String,
     pub is_embedded: bool,
+    pub source: ToolSource,
+    /// Every location that was checked, in resolution order
+    pub checked: Vec<ToolCandidate>,
 }
 
+/// Runs yt-dlp with the resolved toolchain.
+///
+/// Resolving probes every candidate binary, so one instance is kept in managed state
+/// and shared; settings that can change at any time are read on each call instead.
 pub struct Downloader {
-    yt_dlp_path: String,
-    ffmpeg_path: Option<String>,
+    app_handle: AppHandle,
+    yt_dlp: ToolStatus,
+    ffmpeg: ToolStatus,
+    cookies_dir: Option<PathBuf>,
+}
+
+/// The shared `Downloader`, swapped out when the toolchain is refreshed
+pub struct SharedDownloader(RwLock<Arc<Downloader>>);
+
+impl SharedDownloader {
+    pub fn new(downloader: Downloader) -> Self {
+        Self(RwLock::new(Arc::new(downloader)))
+    }
+
+    fn get(&self) -> Arc<Downloader> {
+        self.0.read().unwrap().clone()
+    }
+
+    fn replace(&self, downloader: Arc<Downloader>) {
+        *self.0.write().unwrap() = downloader;
+    }
 }
 
 impl Downloader {
     /// Creates a new Command that won't show a console window on Windows
     #[cfg(windows)]
-    fn create_hidden_command(program: &str) -> Command {
+    pub(crate) fn create_hidden_command(program: &str) -> Command {
         use std::os::windows::process::CommandExt;
         let mut cmd = Command::new(program);
         // CREATE_NO_WINDOW = 0x08000000
@@ -90,145 +262,125 @@ impl Downloader {
     }
     
     #[cfg(not(windows))]
-    fn create_hidden_command(program: &str) -> Command {
+    pub(crate) fn create_hidden_command(program: &str) -> Command {
         Command::new(program)
     }
     
+    /// Resolves the toolchain; prefer `Downloader::shared`, which reuses the resolved instance
     pub fn new(app_handle: &AppHandle) -> Self {
-        // Try to find yt-dlp: first bundled, then PATH
-        let yt_dlp_path = Self::find_yt_dlp(app_handle);
-        let ffmpeg_path = Self::find_ffmpeg(app_handle);
-        Self { yt_dlp_path, ffmpeg_path }
-    }
-
-
-    fn find_yt_dlp(app_handle: &AppHandle) -> String {
-        // Try multiple possible locations for bundled yt-dlp
-        if let Ok(resource_dir) = app_handle.path().resource_dir() {
-            let possible_paths = if cfg!(windows) {
-                vec![
-                    resource_dir.join("binaries").join("yt-dlp.exe"),
-                    resource_dir.join("binaries/yt-dlp.exe"),
-                    resource_dir.join("yt-dlp.exe"),
-                ]
-            } else if cfg!(target_os = "macos") {
-                vec![
-                    resource_dir.join("binaries").join("yt-dlp_macos"),
-                    resource_dir.join("binaries/yt-dlp_macos"),
-                    resource_dir.join("yt-dlp_macos"),
-                    resource_dir.join("binaries").join("yt-dlp"),
-                    resource_dir.join("yt-dlp"),
-                ]
-            } else {
-                vec![
-                    resource_dir.join("binaries").join("yt-dlp"),
-                    resource_dir.join("binaries/yt-dlp"),
-                    resource_dir.join("yt-dlp"),
-                ]
-            };
+        // Custom path, then bundled, app data and (if enabled) PATH
+        let toolchain = crate::toolchain::load_settings(app_handle);
+        let yt_dlp = crate::toolchain::locate(app_handle, &toolchain, Tool::YtDlp);
+        let ffmpeg = crate::toolchain::locate(app_handle, &toolchain, Tool::Ffmpeg);
+        let cookies_dir = crate::cookies::cookies_dir(app_handle).ok();
+        Self { app_handle: app_handle.clone(), yt_dlp, ffmpeg, cookies_dir }
+    }
 
-            for path in &possible_paths {
-                if path.exists() {
-                    println!("[Downloader] Found yt-dlp at: {:?}", path);
-                    return path.to_string_lossy().to_string();
-                }
-            }
-            
-            // Log all checked paths for debugging
-            println!("[Downloader] yt-dlp not found in resource dir. Checked paths:");
-            for path in &possible_paths {
-                println!("  - {:?}", path);
-            }
+    /// The instance held in app state, or a freshly resolved one before setup has finished
+    pub fn shared(app_handle: &AppHandle) -> Arc<Downloader> {
+        match app_handle.try_state::<SharedDownloader>() {
+            Some(shared) => shared.get(),
+            None => Arc::new(Self::new(app_handle)),
         }
+    }
 
-        // Try app data directory (for development or copied binaries)
-        if let Ok(app_data_dir) = app_handle.path().app_data_dir() {
-            let binary_name = if cfg!(windows) { "yt-dlp.exe" } else { "yt-dlp" };
-            let data_path = app_data_dir.join("binaries").join(binary_name);
-            
-            if data_path.exists() {
-                println!("[Downloader] Found yt-dlp in app data: {:?}", data_path);
-                return data_path.to_string_lossy().to_string();
-            }
+    /// Resolves the toolchain again, e.g. after a binary was installed or a path changed.
+    ///
+    /// Running downloads keep the instance they started with.
+    pub async fn refresh(app_handle: &AppHandle) -> Result<Arc<Downloader>, String> {
+        let handle = app_handle.clone();
+        let downloader = tauri::async_runtime::spawn_blocking(move || Arc::new(Self::new(&handle)))
+            .await
+            .map_err(|e| e.to_string())?;
+        if let Some(shared) = app_handle.try_state::<SharedDownloader>() {
+            shared.replace(downloader.clone());
         }
+        Ok(downloader)
+    }
 
-        // Return empty string to indicate not found - DO NOT spawn terminal to check PATH
-        println!("[Downloader] ERROR: yt-dlp not found! The app binaries may not be properly bundled.");
-        String::new()
+    pub fn yt_dlp_status(&self) -> &ToolStatus {
+        &self.yt_dlp
     }
 
-    fn find_ffmpeg(app_handle: &AppHandle) -> Option<String> {
-        // Try multiple possible locations for bundled ffmpeg
-        if let Ok(resource_dir) = app_handle.path().resource_dir() {
-            let possible_paths = if cfg!(windows) {
-                vec![
-                    resource_dir.join("binaries").join("ffmpeg.exe"),
-                    resource_dir.join("binaries/ffmpeg.exe"),
-                    resource_dir.join("ffmpeg.exe"),
-                ]
-            } else {
-                vec![
-                    resource_dir.join("binaries").join("ffmpeg"),
-                    resource_dir.join("binaries/ffmpeg"),
-                    resource_dir.join("ffmpeg"),
-                ]
-            };
+    pub fn ffmpeg_status(&self) -> &ToolStatus {
+        &self.ffmpeg
+    }
 
-            for path in &possible_paths {
-                if path.exists() {
-                    println!("[Downloader] Found ffmpeg at: {:?}", path);
-                    return Some(path.to_string_lossy().to_string());
-                }
-            }
-            
-            // Log all checked paths for debugging
-            println!("[Downloader] ffmpeg not found in resource dir. Checked paths:");
-            for path in &possible_paths {
-                println!("  - {:?}", path);
-            }
-        }
+    fn yt_dlp_path(&self) -> Result<&str, DownloadError> {
+        self.yt_dlp.path.as_deref().ok_or_else(|| DownloadError::BinaryMissing {
+            message: "yt-dlp not found. Install it from the settings, set its path, or enable searching PATH.".to_string(),
+        })
+    }
 
-        // Try app data directory (for development or copied binaries)
-        if let Ok(app_data_dir) = app_handle.path().app_data_dir() {
-            let binary_name = if cfg!(windows) { "ffmpeg.exe" } else { "ffmpeg" };
-            let data_path = app_data_dir.join("binaries").join(binary_name);
-            
-            if data_path.exists() {
-                println!("[Downloader] Found ffmpeg in app data: {:?}", data_path);
-                return Some(data_path.to_string_lossy().to_string());
-            }
+    /// The saved network settings with optional per-request overrides
+    fn network_settings(&self, overrides: Option<&NetworkSettings>) -> NetworkSettings {
+        // Read on every call so settings changes apply without refreshing the toolchain
+        let saved = self
+            .app_handle
+            .try_state::<AppState>()
+            .and_then(|state| state.db.lock().ok().map(|db| NetworkSettings::load(&db)))
+            .unwrap_or_default();
+        match overrides {
+            Some(overrides) => saved.merged(overrides),
+            None => saved,
         }
-
-        // DO NOT spawn terminal to check system PATH - just return None
-        println!("[Downloader] WARNING: FFmpeg not found! Video merging may not work.");
-        None
     }
 
+    /// A hidden yt-dlp command carrying the saved network settings, with optional per-request overrides
+    pub(crate) fn yt_dlp_command(&self, overrides: Option<&NetworkSettings>) -> Result<Command, DownloadError> {
+        let mut command = Self::create_hidden_command(self.yt_dlp_path()?);
+        command.args(self.network_settings(overrides).args());
+        Ok(command)
+    }
 
+    fn cookie_jar(&self, url: &str) -> Option<PathBuf> {
+        self.cookies_dir
+            .as_deref()
+            .and_then(|dir| crate::cookies::jar_for_url(dir, url))
+    }
 
-    pub async fn check_yt_dlp(&self) -> Result<YtDlpInfo, String> {
-        let output = Self::create_hidden_command(&self.yt_dlp_path)
-            .arg("--version")
-            .output()
-            .await
-            .map_err(|e| format!("yt-dlp not found or not working: {}. Please ensure yt-dlp is installed.", e))?;
-
-        if !output.status.success() {
-            return Err("yt-dlp returned an error".to_string());
+    /// `--cookies` with the stored jar for the URL's site, if there is one
+    pub(crate) fn cookie_args(&self, url: &str) -> Vec<String> {
+        match self.cookie_jar(url) {
+            Some(jar) => vec!["--cookies".to_string(), jar.to_string_lossy().to_string()],
+            None => Vec::new(),
         }
+    }
 
-        let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        let is_embedded = self.yt_dlp_path.contains("binaries");
+    /// The media cache context yt-dlp would extract `url` in with these overrides
+    fn cache_context(&self, url: &str, overrides: Option<&NetworkSettings>) -> String {
+        let accounts = Netrc::load(&self.app_handle)
+            .map(|netrc| netrc.accounts())
+            .unwrap_or_default();
+        crate::media_cache::context(
+            &self.network_settings(overrides).args(),
+            self.cookie_jar(url).as_deref(),
+            &accounts,
+        )
+    }
 
-        Ok(YtDlpInfo {
-            version,
-            path: self.yt_dlp_path.clone(),
-            is_embedded,
-        })
+    /// A netrc with the unlocked credentials for one yt-dlp call, removed when dropped
+    pub(crate) fn temp_netrc(&self) -> Result<Option<TempNetrc>, DownloadError> {
+        Ok(Netrc::load(&self.app_handle).map(|netrc| netrc.write()).transpose()?)
     }
 
-    pub async fn get_media_info(&self, url: &str) -> Result<MediaInfo, String> {
-        let output = Self::create_hidden_command(&self.yt_dlp_path)
+    /// The raw yt-dlp JSON for `url`, from the media info cache when a fresh entry exists
+    pub async fn media_info_json(
+        &self,
+        url: &str,
+        network: Option<&NetworkSettings>,
+    ) -> Result<serde_json::Value, DownloadError> {
+        let context = self.cache_context(url, network);
+        if let Some(json) = crate::media_cache::lookup(&self.app_handle, url, &context) {
+            println!("[MediaCache] Using cached info for {}", url);
+            return Ok(json);
+        }
+
+        let netrc = self.temp_netrc()?;
+        let output = self
+            .yt_dlp_command(network)?
+            .args(self.cookie_args(url))
+            .args(netrc.iter().flat_map(TempNetrc::args))
             .args([
                 "-j",
                 "--no-playlist",
@@ -237,15 +389,25 @@ impl Downloader {
             ])
             .output()
             .await
-            .map_err(|e| format!("Failed to execute yt-dlp: {}", e))?;
+            .map_err(|e| DownloadError::from_spawn("yt-dlp", e))?;
 
         if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(format!("yt-dlp error: {}", stderr));
+            let stderr = redact(netrc.as_ref(), &String::from_utf8_lossy(&output.stderr));
+            return Err(DownloadError::from_stderr(&stderr));
         }
 
         let json: serde_json::Value = serde_json::from_slice(&output.stdout)
-            .map_err(|e| format!("Failed to parse yt-dlp output: {}", e))?;
+            .map_err(|e| DownloadError::Parse { message: e.to_string() })?;
+        crate::media_cache::store(&self.app_handle, url, &context, &json);
+        Ok(json)
+    }
+
+    pub async fn get_media_info(
+        &self,
+        url: &str,
+        network: Option<&NetworkSettings>,
+    ) -> Result<MediaInfo, DownloadError> {
+        let json = self.media_info_json(url, network).await?;
 
         let formats = json["formats"]
             .as_array()
@@ -286,6 +448,7 @@ impl Downloader {
             .unwrap_or_default();
 
         Ok(MediaInfo {
+            id: json["id"].as_str().map(|s| s.to_string()),
             title: json["title"].as_str().unwrap_or("Unknown").to_string(),
             duration: json["duration"].as_i64().or_else(|| json["duration"].as_f64().map(|f| f as i64)),
             thumbnail: json["thumbnail"].as_str().map(|s| s.to_string()),
@@ -299,32 +462,63 @@ impl Downloader {
             like_count: json["like_count"].as_i64(),
             upload_date: json["upload_date"].as_str().map(|s| s.to_string()),
             webpage_url: json["webpage_url"].as_str().map(|s| s.to_string()),
+            resolution: json["resolution"].as_str()
+                .filter(|&s| s != "audio only")
+                .map(|s| s.to_string())
+                .or_else(|| json["height"].as_i64().map(|h| format!("{}p", h))),
+            subtitles: crate::subtitles::tracks_from_info(&json),
+            chapters: crate::chapters::chapters_from_json(&json["chapters"]),
         })
     }
 
-    pub async fn start_download(
+    /// Runs a download to completion, emitting `download-progress` events along the way
+    pub async fn run_download(
         &self,
-        request: DownloadRequest,
+        mut request: DownloadRequest,
         app_handle: AppHandle,
-    ) -> Result<(), String> {
-        let (cancel_tx, mut cancel_rx) = tokio::sync::oneshot::channel::<()>();
-        
-        // Store the cancellation sender
-        {
-            let mut downloads = ACTIVE_DOWNLOADS.lock().unwrap();
-            downloads.insert(request.id.clone(), cancel_tx);
-        }
+        rate_limit: Option<u64>,
+    ) -> Result<DownloadOutcome, DownloadError> {
+        self.yt_dlp_path()?;
+        // Registered before planning, which may extract info, so the job can be
+        // paused or cancelled as soon as the queue marks it running
+        let id = request.id.clone();
+        let (_active, mut cancel_rx) = ActiveDownload::register(&id);
+        let plan = tokio::select! {
+            plan = crate::naming::plan_output(&app_handle, self, &mut request) => plan?,
+            signal = &mut cancel_rx => {
+                return Ok(stopped_before_start(&app_handle, &id, signal.unwrap_or(StopSignal::Cancel)));
+            }
+        };
+        let (stem, overwrite) = match plan {
+            OutputPlan::Download { stem, overwrite } => (stem, overwrite),
+            OutputPlan::Existing(done) => {
+                let outcome = DownloadOutcome::Completed(done);
+                emit_outcome(&app_handle, &request.id, &outcome, 100.0, None);
+                return Ok(outcome);
+            }
+        };
 
         let mut args = vec![
             "--progress".to_string(),
             "--newline".to_string(),
+            // Pick up .part files left behind by a paused or interrupted run
+            "--continue".to_string(),
             "--no-warnings".to_string(),
             "--progress-template".to_string(),
-            "download:%(progress._percent_str)s|%(progress._speed_str)s|%(progress._eta_str)s|%(progress._downloaded_bytes_str)s|%(progress._total_bytes_str)s".to_string(),
+            "download:%(progress._percent_str)s|%(progress._speed_str)s|%(progress._eta_str)s|%(progress.downloaded_bytes)s|%(progress.total_bytes,progress.total_bytes_estimate)s|%(progress.filename)s".to_string(),
+            // --print below implies --quiet, so post-processing needs its own template
+            "--progress-template".to_string(),
+            format!("postprocess:{}%(progress.postprocessor)s", POSTPROCESS_MARKER),
+            // Report the resolved metadata so history can show the real title
+            "--print".to_string(),
+            format!("before_dl:{}%(.{{id,title,extractor,extractor_key,thumbnail}})j", INFO_MARKER),
+            // Report where the finished file ended up after all post-processing
+            "--print".to_string(),
+            format!("after_move:{}%(filepath)s", FILEPATH_MARKER),
         ];
 
         // Add ffmpeg location if available
-        if let Some(ffmpeg) = &self.ffmpeg_path {
+        if let Some(ffmpeg) = &self.ffmpeg.path {
             // Get the directory containing ffmpeg, not the full path to the binary
             if let Some(ffmpeg_dir) = std::path::Path::new(ffmpeg).parent() {
                 args.extend(["--ffmpeg-location".to_string(), ffmpeg_dir.to_string_lossy().to_string()]);
@@ -337,19 +531,18 @@ impl Downloader {
             println!("[Downloader] Warning: FFmpeg not found. Some downloads may fail.");
         }
 
-        // Output template
-        let output_template = format!("{}/%(title)s.%(ext)s", request.output_path);
-        args.extend(["-o".to_string(), output_template]);
+        // Output template, already resolved against the naming settings
+        args.extend(["-o".to_string(), crate::clips::output_template(&request.clip_ranges, &stem)]);
+        if overwrite {
+            args.push("--force-overwrites".to_string());
+        }
 
         // Quality/format selection
         if request.audio_only {
-            args.extend([
-                "-x".to_string(),
-                "--audio-format".to_string(),
-                "mp3".to_string(),
-                "--audio-quality".to_string(),
-                "0".to_string(), // Best quality
-            ]);
+            if let Some(format) = request.audio_format {
+                crate::audio::validate(format, request.audio_quality)?;
+            }
+            args.extend(crate::audio::extraction_args(request.audio_format, request.audio_quality));
         } else if let Some(format) = &request.format {
             if !format.is_empty() {
                 args.extend(["-f".to_string(), format.clone()]);
@@ -369,6 +562,33 @@ impl Downloader {
             args.extend(["--merge-output-format".to_string(), "mp4".to_string()]);
         }
 
+        // Subtitles, as separate files or embedded into the container
+        if let Some(subtitles) = &request.subtitles {
+            crate::subtitles::validate(&request)?;
+            args.extend(crate::subtitles::args(subtitles));
+            args.extend([
+                "--print".to_string(),
+                format!("after_move:{}%(requested_subtitles)j", SUBTITLES_MARKER),
+            ]);
+        }
+
+        // Bandwidth limit chosen by the scheduler, in bytes per second
+        if let Some(limit) = rate_limit {
+            args.extend(["--limit-rate".to_string(), limit.to_string()]);
+        }
+
+        // Time-range clipping
+        args.extend(crate::clips::args(&request.clip_ranges));
+
+        // Chapter markers and per-chapter files
+        args.extend(crate::chapters::args(&request, &stem));
+        if request.split_chapters {
+            args.extend([
+                "--print".to_string(),
+                format!("after_move:{}%(chapters)j", CHAPTERS_MARKER),
+            ]);
+        }
+
         // Embed options
         if request.embed_thumbnail {
             args.push("--embed-thumbnail".to_string());
@@ -377,167 +597,283 @@ impl Downloader {
             args.push("--embed-metadata".to_string());
         }
 
-        // Add URL
-        args.push(request.url.clone());
+        // Add URL, or reuse the cached extraction so yt-dlp doesn't fetch the page again
+        let context = self.cache_context(&request.url, request.network.as_ref());
+        let info_json = InfoJsonFile::for_url(&app_handle, &request.url, &context);
+        match &info_json {
+            Some(info_json) => args.extend(info_json.args()),
+            None => args.push(request.url.clone()),
+        }
+
+        if let Some(signal) = stop_requested(&mut cancel_rx) {
+            return Ok(stopped_before_start(&app_handle, &id, signal));
+        }
 
-        let mut child = Self::create_hidden_command(&self.yt_dlp_path)
+        // Kept until yt-dlp exits; the file is removed when this goes out of scope
+        let netrc = self.temp_netrc()?;
+        let mut child = self
+            .yt_dlp_command(request.network.as_ref())?
+            .args(self.cookie_args(&request.url))
+            .args(netrc.iter().flat_map(TempNetrc::args))
             .args(&args)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()
-            .map_err(|e| format!("Failed to start download: {}", e))?;
+            .map_err(|e| DownloadError::from_spawn("yt-dlp", e))?;
 
-        let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
-        let stderr = child.stderr.take().ok_or("Failed to capture stderr")?;
+        let stdout = child.stdout.take()
+            .ok_or_else(|| DownloadError::internal("Failed to capture stdout"))?;
+        let stderr = child.stderr.take()
+            .ok_or_else(|| DownloadError::internal("Failed to capture stderr"))?;
         let mut stdout_reader = BufReader::new(stdout).lines();
         let mut stderr_reader = BufReader::new(stderr).lines();
 
-        let id = request.id.clone();
-        let app = app_handle.clone();
-        let yt_dlp_path = self.yt_dlp_path.clone();
-
-        tokio::spawn(async move {
-            let mut last_progress = 0.0_f64;
-            let mut error_output = String::new();
-
-            loop {
-                tokio::select! {
-                    _ = &mut cancel_rx => {
-                        // Download cancelled
-                        let _ = child.kill().await;
-                        let _ = app.emit("download-progress", DownloadProgress {
-                            id: id.clone(),
-                            progress: last_progress,
-                            speed: String::new(),
-                            eta: String::new(),
-                            status: "cancelled".to_string(),
-                            downloaded_bytes: None,
-                            total_bytes: None,
-                            filename: None,
-                        });
-                        break;
-                    }
-                    result = stdout_reader.next_line() => {
-                        match result {
-                            Ok(Some(line)) => {
-                                println!("[yt-dlp stdout] {}", line);
-                                
+        let app = app_handle;
+        let mut last_progress = 0.0_f64;
+        let mut error_output = String::new();
+        let mut stop_signal = None;
+        let mut current_filename: Option<String> = None;
+        let mut final_path: Option<String> = None;
+        // Several clip ranges are moved into place one file at a time
+        let mut moved_paths = Vec::new();
+        let mut subtitle_files = Vec::new();
+        let mut chapters = Vec::new();
+
+        loop {
+            tokio::select! {
+                signal = &mut cancel_rx => {
+                    // Download cancelled or paused; yt-dlp leaves its .part files on kill
+                    let _ = child.kill().await;
+                    stop_signal = Some(signal.unwrap_or(StopSignal::Cancel));
+                    break;
+                }
+                result = stdout_reader.next_line() => {
+                    match result {
+                        Ok(Some(line)) => {
+                            println!("[yt-dlp stdout] {}", redact(netrc.as_ref(), &line));
+
+                            if let Some(path) = line.strip_prefix(FILEPATH_MARKER) {
+                                final_path = Some(path.trim().to_string());
+                                moved_paths.push(path.trim().to_string());
+                            } else if let Some(requested) = line.strip_prefix(SUBTITLES_MARKER) {
+                                let embedded = request.subtitles.as_ref().is_some_and(|s| s.embed);
+                                subtitle_files = crate::subtitles::files_from_requested(requested, embedded);
+                            } else if let Some(list) = line.strip_prefix(CHAPTERS_MARKER) {
+                                chapters = serde_json::from_str(list)
+                                    .map(|json| crate::chapters::chapters_from_json(&json))
+                                    .unwrap_or_default();
+                            } else if let Some(info) = line.strip_prefix(INFO_MARKER) {
+                                if let Ok(summary) = serde_json::from_str::<MediaSummary>(info) {
+                                    crate::queue::record_metadata(&app, &id, &request.url, &summary);
+                                }
+                            } else if let Some(progress) = parse_progress_template(&line) {
                                 // Try to parse progress from various formats
-                                if let Some(progress) = parse_progress_template(&line) {
-                                    last_progress = progress.0;
-                                    let event = DownloadProgress {
-                                        id: id.clone(),
-                                        progress: progress.0,
-                                        speed: progress.1,
-                                        eta: progress.2,
-                                        status: "downloading".to_string(),
-                                        downloaded_bytes: None,
-                                        total_bytes: None,
-                                        filename: None,
-                                    };
-                                    let _ = app.emit("download-progress", event);
-                                } else if let Some(progress) = parse_progress(&line) {
-                                    last_progress = progress.0;
-                                    let event = DownloadProgress {
-                                        id: id.clone(),
-                                        progress: progress.0,
-                                        speed: progress.1,
-                                        eta: progress.2,
-                                        status: "downloading".to_string(),
-                                        downloaded_bytes: None,
-                                        total_bytes: None,
-                                        filename: None,
-                                    };
-                                    let _ = app.emit("download-progress", event);
-                                } else if line.contains("[Merger]") || line.contains("[ExtractAudio]") || line.contains("[ffmpeg]") {
-                                    // During merging/post-processing, show 99% progress
-                                    let event = DownloadProgress {
-                                        id: id.clone(),
-                                        progress: 99.0,
-                                        speed: "Merging...".to_string(),
-                                        eta: "".to_string(),
-                                        status: "downloading".to_string(),
-                                        downloaded_bytes: None,
-                                        total_bytes: None,
-                                        filename: None,
-                                    };
-                                    let _ = app.emit("download-progress", event);
+                                if progress.filename.is_some() {
+                                    current_filename = progress.filename.clone();
                                 }
+                                last_progress = progress.percent.unwrap_or(last_progress);
+                                let event = DownloadProgress {
+                                    id: id.clone(),
+                                    progress: last_progress,
+                                    speed: progress.speed,
+                                    eta: progress.eta,
+                                    status: "downloading".to_string(),
+                                    downloaded_bytes: progress.downloaded_bytes,
+                                    total_bytes: progress.total_bytes,
+                                    filename: current_filename.clone(),
+                                    error: None,
+                                };
+                                let _ = app.emit("download-progress", event);
+                            } else if let Some(progress) = parse_progress(&line) {
+                                last_progress = progress.0;
+                                let event = DownloadProgress {
+                                    id: id.clone(),
+                                    progress: progress.0,
+                                    speed: progress.1,
+                                    eta: progress.2,
+                                    status: "downloading".to_string(),
+                                    downloaded_bytes: None,
+                                    total_bytes: None,
+                                    filename: current_filename.clone(),
+                                    error: None,
+                                };
+                                let _ = app.emit("download-progress", event);
+                            } else if line.starts_with(POSTPROCESS_MARKER) || line.contains("[Merger]") || line.contains("[ExtractAudio]") || line.contains("[ffmpeg]") {
+                                // During merging/post-processing, show 99% progress
+                                let event = DownloadProgress {
+                                    id: id.clone(),
+                                    progress: 99.0,
+                                    speed: "Merging...".to_string(),
+                                    eta: "".to_string(),
+                                    status: "downloading".to_string(),
+                                    downloaded_bytes: None,
+                                    total_bytes: None,
+                                    filename: current_filename.clone(),
+                                    error: None,
+                                };
+                                let _ = app.emit("download-progress", event);
                             }
-                            Ok(None) => break,
-                            Err(_) => break,
                         }
+                        Ok(None) => break,
+                        Err(_) => break,
                     }
-                    result = stderr_reader.next_line() => {
-                        match result {
-                            Ok(Some(line)) => {
-                                error_output.push_str(&line);
-                                error_output.push('\n');
-                            }
-                            Ok(None) => {},
-                            Err(_) => {},
+                }
+                result = stderr_reader.next_line() => {
+                    match result {
+                        Ok(Some(line)) => {
+                            error_output.push_str(&line);
+                            error_output.push('\n');
                         }
+                        Ok(None) => {},
+                        Err(_) => {},
                     }
                 }
             }
+        }
 
-            // Wait for the process to finish
-            let status = child.wait().await;
+        // Wait for the process to finish
+        let status = child.wait().await;
 
-            // Clean up active downloads
-            {
-                let mut downloads = ACTIVE_DOWNLOADS.lock().unwrap();
-                downloads.remove(&id);
+        let outcome = if let Some(signal) = stop_signal {
+            signal.outcome()
+        } else {
+            // Drain whatever stderr is left so the failure reason is complete
+            while let Ok(Some(line)) = stderr_reader.next_line().await {
+                error_output.push_str(&line);
+                error_output.push('\n');
             }
 
-            // Emit final status
-            let final_status = match status {
-                Ok(exit_status) if exit_status.success() => "completed",
-                _ => "failed",
-            };
+            match status {
+                Ok(exit_status) if exit_status.success() => {
+                    let size_bytes = final_path
+                        .as_ref()
+                        .and_then(|path| std::fs::metadata(path).ok())
+                        .map(|metadata| metadata.len() as i64);
+                    let chapter_files = if request.split_chapters {
+                        crate::chapters::collect_files(&stem, &chapters)
+                    } else {
+                        Vec::new()
+                    };
+                    DownloadOutcome::Completed(CompletedDownload {
+                        filepath: final_path,
+                        size_bytes,
+                        subtitles: subtitle_files,
+                        chapters: chapter_files,
+                        clips: crate::clips::collect_files(&request.clip_ranges, &moved_paths),
+                    })
+                }
+                _ => DownloadOutcome::Failed(DownloadError::from_stderr(&redact(netrc.as_ref(), &error_output))),
+            }
+        };
 
-            let _ = app.emit("download-progress", DownloadProgress {
-                id: id.clone(),
-                progress: if final_status == "completed" { 100.0 } else { last_progress },
-                speed: String::new(),
-                eta: String::new(),
-                status: final_status.to_string(),
-                downloaded_bytes: None,
-                total_bytes: None,
-                filename: None,
-            });
-        });
+        // Failures are announced by the queue, which decides whether to retry;
+        // a relaunched download just keeps reporting progress from its next run
+        if matches!(outcome, DownloadOutcome::Failed(_) | DownloadOutcome::Relaunched) {
+            // The cached format URLs may have expired; a retry extracts them again
+            if info_json.is_some() && matches!(outcome, DownloadOutcome::Failed(_)) {
+                crate::media_cache::invalidate(&app, &request.url);
+            }
+            return Ok(outcome);
+        }
 
-        Ok(())
+        emit_outcome(&app, &id, &outcome, last_progress, current_filename);
+        Ok(outcome)
     }
 }
 
-fn parse_progress_template(line: &str) -> Option<(f64, String, String)> {
-    // Parse our custom progress template: percent|speed|eta|downloaded|total
-    // yt-dlp outputs like: "50.0%|10.5MiB/s|00:05|52.5MiB|105.0MiB"
-    let parts: Vec<&str> = line.split('|').collect();
-    if parts.len() >= 3 {
-        // Clean the percent string - remove spaces, %, and any other characters
-        let percent_str = parts[0]
-            .trim()
-            .replace('%', "")
-            .replace(' ', "")
-            .chars()
-            .filter(|c| c.is_ascii_digit() || *c == '.' || *c == '-')
-            .collect::<String>();
-        
-        let progress = percent_str.parse::<f64>().ok()?;
-        
-        // Clean speed and eta strings
-        let speed = parts[1].trim().replace("N/A", "").to_string();
-        let eta = parts[2].trim().replace("N/A", "").to_string();
-        
-        // Log for debugging
-        println!("[Progress] {}% | {} | {}", progress, speed, eta);
-        
-        return Some((progress, speed, eta));
+/// Emits the final status, with the real path and size once the file is in place
+/// The outcome of a download stopped before yt-dlp was started
+fn stopped_before_start(app: &AppHandle, id: &str, signal: StopSignal) -> DownloadOutcome {
+    let outcome = signal.outcome();
+    // A relaunched download keeps reporting progress from its next run
+    if outcome != DownloadOutcome::Relaunched {
+        emit_outcome(app, id, &outcome, 0.0, None);
     }
-    None
+    outcome
+}
+
+fn emit_outcome(
+    app: &AppHandle,
+    id: &str,
+    outcome: &DownloadOutcome,
+    last_progress: f64,
+    current_filename: Option<String>,
+) {
+    let (progress, size_bytes, filename) = match outcome {
+        DownloadOutcome::Completed(done) => (100.0, done.size_bytes, done.filepath.clone()),
+        _ => (last_progress, None, current_filename),
+    };
+    let _ = app.emit("download-progress", DownloadProgress {
+        id: id.to_string(),
+        progress,
+        speed: String::new(),
+        eta: String::new(),
+        status: outcome.status().to_string(),
+        downloaded_bytes: size_bytes,
+        total_bytes: size_bytes,
+        filename,
+        error: None,
+    });
+}
+
+/// One line of our `--progress-template` output
+struct ProgressLine {
+    percent: Option<f64>,
+    speed: String,
+    eta: String,
+    downloaded_bytes: Option<i64>,
+    total_bytes: Option<i64>,
+    filename: Option<String>,
+}
+
+/// Parses a raw byte count from the template; yt-dlp prints "NA" when unknown
+/// and a float for `total_bytes_estimate`
+fn parse_byte_count(value: &str) -> Option<i64> {
+    value.trim().parse::<f64>().ok().filter(|v| *v >= 0.0).map(|v| v as i64)
+}
+
+fn parse_progress_template(line: &str) -> Option<ProgressLine> {
+    // Parse our custom progress template: percent|speed|eta|downloaded|total|filename
+    // yt-dlp outputs like: "50.0%|10.5MiB/s|00:05|55050240|110100480|/path/video.f137.mp4"
+    // The filename goes last since it may itself contain '|'
+    let parts: Vec<&str> = line.splitn(6, '|').collect();
+    if parts.len() < 3 {
+        return None;
+    }
+
+    // Clean the percent string - remove spaces, %, and any other characters
+    let percent_str = parts[0]
+        .trim()
+        .replace(['%', ' '], "")
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == '.' || *c == '-')
+        .collect::<String>();
+
+    let downloaded_bytes = parts.get(3).and_then(|v| parse_byte_count(v));
+    let total_bytes = parts.get(4).and_then(|v| parse_byte_count(v));
+
+    // Fall back to the byte counts when yt-dlp can't print a percentage
+    let percent = percent_str.parse::<f64>().ok().or_else(|| match (downloaded_bytes, total_bytes) {
+        (Some(done), Some(total)) if total > 0 => Some(done as f64 * 100.0 / total as f64),
+        _ => None,
+    });
+    if percent.is_none() && downloaded_bytes.is_none() {
+        return None;
+    }
+
+    // Clean speed and eta strings
+    let speed = parts[1].trim().replace("N/A", "").to_string();
+    let eta = parts[2].trim().replace("N/A", "").to_string();
+    let filename = parts
+        .get(5)
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty() && *v != "NA")
+        .map(|v| v.to_string());
+
+    // Log for debugging
+    println!("[Progress] {:?}% | {} | {} | {:?}/{:?}", percent, speed, eta, downloaded_bytes, total_bytes);
+
+    Some(ProgressLine { percent, speed, eta, downloaded_bytes, total_bytes, filename })
 }
 
 fn parse_progress(line: &str) -> Option<(f64, String, String)> {
@@ -576,41 +912,107 @@ fn parse_progress(line: &str) -> Option<(f64, String, String)> {
 
 // Tauri commands for downloading
 #[tauri::command]
-pub async fn check_yt_dlp(app_handle: AppHandle) -> Result<YtDlpInfo, String> {
-    let downloader = Downloader::new(&app_handle);
-    downloader.check_yt_dlp().await
+pub async fn check_yt_dlp(app_handle: AppHandle) -> Result<YtDlpInfo, DownloadError> {
+    // Resolved once and cached; `refresh_toolchain` looks again
+    let status = Downloader::shared(&app_handle).yt_dlp_status().clone();
+    match (status.path, status.version, status.source) {
+        (Some(path), Some(version), Some(source)) => Ok(YtDlpInfo {
+            version,
+            path,
+            is_embedded: source == ToolSource::Bundled,
+            source,
+            checked: status.checked,
+        }),
+        _ => Err(DownloadError::BinaryMissing {
+            message: format!(
+                "yt-dlp not found. Checked: {}",
+                status
+                    .checked
+                    .iter()
+                    .map(|candidate| candidate.path.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }),
+    }
 }
 
 #[tauri::command]
-pub async fn get_media_info(app_handle: AppHandle, url: String) -> Result<MediaInfo, String> {
-    let downloader = Downloader::new(&app_handle);
-    downloader.get_media_info(&url).await
+pub async fn get_media_info(
+    app_handle: AppHandle,
+    url: String,
+    network: Option<NetworkSettings>,
+) -> Result<MediaInfo, DownloadError> {
+    if let Some(network) = &network {
+        network.validate().map_err(DownloadError::invalid)?;
+    }
+    let downloader = Downloader::shared(&app_handle);
+    downloader.get_media_info(&url, network.as_ref()).await
 }
 
 #[tauri::command]
 pub async fn start_download(
     app_handle: AppHandle,
     request: DownloadRequest,
-) -> Result<(), String> {
-    let downloader = Downloader::new(&app_handle);
-    downloader.start_download(request, app_handle).await
+) -> Result<(), DownloadError> {
+    if !request.clip_ranges.is_empty() {
+        crate::clips::validate(&request)?;
+        let info = Downloader::shared(&app_handle)
+            .get_media_info(&request.url, request.network.as_ref())
+            .await?;
+        crate::clips::validate_duration(&request.clip_ranges, info.duration)?;
+    }
+    crate::queue::enqueue(&app_handle, request)
 }
 
 #[tauri::command]
-pub async fn cancel_download(id: String) -> Result<(), String> {
+pub async fn cancel_download(app_handle: AppHandle, id: String) -> Result<(), String> {
     let sender = {
         let mut downloads = ACTIVE_DOWNLOADS.lock().unwrap();
         downloads.remove(&id)
     };
 
     if let Some(tx) = sender {
-        let _ = tx.send(());
+        let _ = tx.send(StopSignal::Cancel);
+        Ok(())
+    } else if crate::queue::cancel_waiting(&app_handle, &id) {
         Ok(())
     } else {
         Err("Download not found or already finished".to_string())
     }
 }
 
+#[tauri::command]
+pub async fn pause_download(app_handle: AppHandle, id: String) -> Result<(), String> {
+    let sender = {
+        let mut downloads = ACTIVE_DOWNLOADS.lock().unwrap();
+        downloads.remove(&id)
+    };
+
+    if let Some(tx) = sender {
+        let _ = tx.send(StopSignal::Pause);
+        Ok(())
+    } else if crate::queue::pause_pending(&app_handle, &id) {
+        Ok(())
+    } else {
+        Err("Download not found or already finished".to_string())
+    }
+}
+
+/// Stops a running download so the scheduler can start it again; returns whether it was running
+pub fn relaunch_download(id: &str) -> bool {
+    let sender = {
+        let mut downloads = ACTIVE_DOWNLOADS.lock().unwrap();
+        downloads.remove(id)
+    };
+    sender.is_some_and(|tx| tx.send(StopSignal::Relaunch).is_ok())
+}
+
+#[tauri::command]
+pub async fn resume_download(app_handle: AppHandle, id: String) -> Result<(), String> {
+    crate::queue::resume(&app_handle, &id)
+}
+
 #[tauri::command]
 pub async fn get_supported_platforms() -> Result<Vec<String>, String> {
     // Return a list of popular supported platforms
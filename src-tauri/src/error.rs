@@ -0,0 +1,158 @@
+use crate::retry::RetryableClass;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// A yt-dlp failure sorted into a category the frontend can act on.
+///
+/// Serialized as `{ "kind": "geo_restricted", "message": "..." }`; the `kind`
+/// values are part of the frontend contract and must stay stable.
+#[derive(Error, Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DownloadError {
+    #[error("Media unavailable: {message}")]
+    Unavailable { message: String },
+    #[error("Geo-restricted: {message}")]
+    GeoRestricted { message: String },
+    #[error("Login required: {message}")]
+    LoginRequired { message: String },
+    #[error("Rate limited: {message}")]
+    RateLimited { message: String },
+    #[error("Unsupported URL: {message}")]
+    UnsupportedUrl { message: String },
+    #[error("Post-processing failed: {message}")]
+    PostprocessFailed { message: String },
+    #[error("Required binary missing: {message}")]
+    BinaryMissing { message: String },
+    #[error("Server error: {message}")]
+    ServerError { message: String },
+    #[error("Network error: {message}")]
+    Network { message: String },
+    #[error("Failed to parse yt-dlp output: {message}")]
+    Parse { message: String },
+    #[error("Invalid request: {message}")]
+    InvalidRequest { message: String },
+    #[error("Already downloaded: {message}")]
+    Duplicate { message: String, existing_id: String },
+    #[error("{message}")]
+    Internal { message: String },
+    #[error("yt-dlp error: {message}")]
+    Other { message: String },
+}
+
+impl DownloadError {
+    /// Sorts yt-dlp's stderr into a category, keeping the most relevant line as the message
+    pub fn from_stderr(stderr: &str) -> Self {
+        let message = primary_message(stderr);
+        let lower = stderr.to_lowercase();
+        let contains_any = |markers: &[&str]| markers.iter().any(|marker| lower.contains(marker));
+
+        if contains_any(&["unsupported url", "is not a valid url", "no suitable extractor"]) {
+            DownloadError::UnsupportedUrl { message }
+        } else if contains_any(&[
+            "not available in your country",
+            "geo restrict",
+            "geo-restrict",
+            "blocked it in your country",
+            "not available from your location",
+        ]) {
+            DownloadError::GeoRestricted { message }
+        } else if contains_any(&["http error 429", "too many requests", "rate-limit", "rate limit"]) {
+            DownloadError::RateLimited { message }
+        } else if contains_any(&[
+            "sign in",
+            "login required",
+            "log in",
+            "members-only",
+            "join this channel",
+            "only available for registered users",
+            "--username",
+            "use --cookies",
+            "requires authentication",
+            "private video",
+        ]) {
+            DownloadError::LoginRequired { message }
+        } else if contains_any(&[
+            "video unavailable",
+            "is unavailable",
+            "has been removed",
+            "does not exist",
+            "http error 404",
+            "http error 410",
+            "no video formats found",
+            "requested format is not available",
+        ]) {
+            DownloadError::Unavailable { message }
+        } else if contains_any(&[
+            "ffmpeg not found",
+            "ffprobe not found",
+            "ffprobe and ffmpeg not found",
+            "ffmpeg is not installed",
+        ]) {
+            DownloadError::BinaryMissing { message }
+        } else if contains_any(&["postprocessing:", "conversion failed", "error merging", "ffmpeg exited"]) {
+            DownloadError::PostprocessFailed { message }
+        } else if contains_any(&["http error 500", "http error 502", "http error 503", "http error 504"]) {
+            DownloadError::ServerError { message }
+        } else if contains_any(&[
+            "connection reset",
+            "connection aborted",
+            "connection refused",
+            "timed out",
+            "temporary failure in name resolution",
+            "network is unreachable",
+            "remote end closed connection",
+            "incompleteread",
+        ]) {
+            DownloadError::Network { message }
+        } else {
+            DownloadError::Other { message }
+        }
+    }
+
+    /// Maps a failure to launch a binary, treating "not found" as a missing binary
+    pub fn from_spawn(binary: &str, error: std::io::Error) -> Self {
+        if error.kind() == std::io::ErrorKind::NotFound {
+            DownloadError::BinaryMissing {
+                message: format!("{} not found. The app binaries may not be properly bundled.", binary),
+            }
+        } else {
+            DownloadError::Internal {
+                message: format!("Failed to execute {}: {}", binary, error),
+            }
+        }
+    }
+
+    pub fn invalid(message: impl Into<String>) -> Self {
+        DownloadError::InvalidRequest { message: message.into() }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        DownloadError::Internal { message: message.into() }
+    }
+
+    /// The retry class this failure belongs to, if it is transient
+    pub fn retryable_class(&self) -> Option<RetryableClass> {
+        match self {
+            DownloadError::RateLimited { .. } => Some(RetryableClass::RateLimited),
+            DownloadError::ServerError { .. } => Some(RetryableClass::ServerError),
+            DownloadError::Network { .. } => Some(RetryableClass::Network),
+            _ => None,
+        }
+    }
+}
+
+impl From<String> for DownloadError {
+    fn from(message: String) -> Self {
+        DownloadError::Internal { message }
+    }
+}
+
+/// Picks the last `ERROR:` line from stderr, falling back to the whole output
+fn primary_message(stderr: &str) -> String {
+    stderr
+        .lines()
+        .rev()
+        .find_map(|line| line.trim().strip_prefix("ERROR:"))
+        .map(|line| line.trim().to_string())
+        .unwrap_or_else(|| stderr.trim().to_string())
+}
@@ -1,12 +1,36 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod audio;
+mod backup;
+mod bandwidth;
+mod batch;
+mod binaries;
+mod canonical;
+mod chapters;
+mod clips;
 mod commands;
+mod cookies;
+mod credentials;
 mod database;
 mod downloader;
+mod error;
+mod media_cache;
+mod migrations;
+mod naming;
+mod network;
+mod playlist;
+mod queue;
+mod retry;
+mod scheduler;
+mod subtitles;
+mod toolchain;
 
 use commands::AppState;
+use credentials::CredentialStore;
 use database::Database;
+use downloader::{Downloader, SharedDownloader};
+use scheduler::Scheduler;
 use std::sync::Mutex;
 use tauri::Manager;
 
@@ -29,8 +53,35 @@ pub fn run() {
             let db = Database::new(app_data_dir)
                 .expect("Failed to initialize database");
 
+            // History rows from before canonical ids existed can't be checked for duplicates
+            match db.backfill_canonical_ids(canonical::canonical_id) {
+                Ok(0) => {}
+                Ok(filled) => println!("[Duplicates] Computed canonical ids for {} download(s)", filled),
+                Err(e) => println!("[Duplicates] Failed to compute canonical ids: {}", e),
+            }
+
+            // Scheduler limits are persisted in settings
+            let scheduler = Scheduler::from_settings(&db);
+
             // Store in app state
             app.manage(AppState { db: Mutex::new(db) });
+            app.manage(scheduler);
+            app.manage(CredentialStore::new());
+
+            // Resolve yt-dlp and ffmpeg once; commands and the scheduler share the result
+            app.manage(SharedDownloader::new(Downloader::new(app.handle())));
+
+            // Netrc files only live as long as a yt-dlp call; drop any left by a crash
+            credentials::clear_temp_netrc(app.handle());
+            media_cache::clear_info_files(app.handle());
+
+            // Resume downloads that were queued or running when the app last exited
+            if let Err(e) = queue::recover(app.handle()) {
+                println!("[Queue] Failed to recover queued downloads: {}", e);
+            }
+
+            // Move running downloads onto the new limit when a bandwidth window starts or ends
+            bandwidth::start_schedule_timer(app.handle().clone());
 
             Ok(())
         })
@@ -55,9 +106,80 @@ pub fn run() {
             downloader::get_media_info,
             downloader::start_download,
             downloader::cancel_download,
+            downloader::pause_download,
+            downloader::resume_download,
             downloader::get_supported_platforms,
             downloader::get_default_download_path,
             downloader::get_download_folder_size,
+            // Binary manager commands
+            binaries::get_binary_sources,
+            binaries::set_binary_sources,
+            binaries::get_binaries_status,
+            binaries::install_binary,
+            binaries::rollback_binary,
+            // Tool discovery commands
+            toolchain::get_toolchain_settings,
+            toolchain::set_toolchain_settings,
+            toolchain::get_toolchain_status,
+            toolchain::refresh_toolchain,
+            // Media info cache commands
+            media_cache::get_media_cache_settings,
+            media_cache::set_media_cache_settings,
+            media_cache::get_media_cache,
+            media_cache::purge_media_cache,
+            // Duplicate detection commands
+            canonical::get_duplicate_settings,
+            canonical::set_duplicate_settings,
+            canonical::find_duplicate_download,
+            // Export and import commands
+            backup::export_database,
+            backup::import_database,
+            // Playlist commands
+            playlist::get_playlist_info,
+            playlist::enqueue_playlist_entries,
+            // File naming commands
+            naming::get_naming_settings,
+            naming::set_naming_settings,
+            naming::preview_filename_template,
+            // Subtitle commands
+            subtitles::get_download_subtitles,
+            // Chapter commands
+            chapters::get_download_chapters,
+            // Bandwidth commands
+            bandwidth::get_bandwidth_settings,
+            bandwidth::set_bandwidth_settings,
+            bandwidth::get_current_bandwidth_limit,
+            // Cookie commands
+            cookies::list_cookie_jars,
+            cookies::import_cookie_file,
+            cookies::import_browser_cookies,
+            cookies::delete_cookie_jar,
+            // Credential commands
+            credentials::get_credential_store_status,
+            credentials::unlock_credential_store,
+            credentials::lock_credential_store,
+            credentials::change_credential_passphrase,
+            credentials::reset_credential_store,
+            credentials::list_credentials,
+            credentials::save_credential,
+            credentials::delete_credential,
+            // Network commands
+            network::get_network_settings,
+            network::set_network_settings,
+            // Batch import commands
+            batch::import_batch,
+            // Queue commands
+            queue::get_download_queue,
+            queue::remove_from_queue,
+            queue::clear_finished_queue,
+            // Retry commands
+            retry::get_retry_policy,
+            retry::set_retry_policy,
+            retry::get_download_attempts,
+            // Scheduler commands
+            scheduler::get_scheduler_status,
+            scheduler::set_max_concurrent_downloads,
+            scheduler::set_platform_concurrency_limit,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
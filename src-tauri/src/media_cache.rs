@@ -0,0 +1,288 @@
+use crate::commands::AppState;
+use crate::database::{Database, MediaCacheEntry};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+
+// Settings key the cache settings are persisted under
+const MEDIA_CACHE_KEY: &str = "media_cache";
+
+const DEFAULT_TTL_SECS: u64 = 60 * 60;
+// Format URLs in the info expire, so entries can't be kept for long
+const MAX_TTL_SECS: u64 = 6 * 60 * 60;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MediaCacheSettings {
+    /// How long extracted info is reused; 0 turns the cache off
+    pub ttl_secs: u64,
+}
+
+impl Default for MediaCacheSettings {
+    fn default() -> Self {
+        Self { ttl_secs: DEFAULT_TTL_SECS }
+    }
+}
+
+impl MediaCacheSettings {
+    pub fn load(db: &Database) -> Self {
+        db.get_setting(MEDIA_CACHE_KEY)
+            .ok()
+            .flatten()
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.ttl_secs > MAX_TTL_SECS {
+            return Err(format!(
+                "Cache lifetime can be at most {} hours",
+                MAX_TTL_SECS / 3600
+            ));
+        }
+        Ok(())
+    }
+
+    /// Entries fetched before this time, in milliseconds, are stale
+    fn fresh_after(&self) -> i64 {
+        chrono::Utc::now().timestamp_millis() - (self.ttl_secs as i64) * 1000
+    }
+}
+
+fn is_playlist_url(url: &str) -> bool {
+    let url = url.to_lowercase();
+    url.contains("/playlist") || url.contains("?list=") || url.contains("&list=")
+}
+
+/// Identifies what an extraction depends on besides the URL: the yt-dlp network
+/// arguments, the cookie jar and the accounts in the netrc.
+///
+/// Info fetched through another proxy or while logged in differently is not reused.
+/// The jar's modification time is included so importing new cookies starts over.
+pub fn context(network_args: &[String], cookie_jar: Option<&Path>, accounts: &[String]) -> String {
+    let mut hasher = Sha256::new();
+    for arg in network_args {
+        hasher.update(arg.as_bytes());
+        hasher.update([0]);
+    }
+    if let Some(jar) = cookie_jar {
+        hasher.update(jar.to_string_lossy().as_bytes());
+        let modified = std::fs::metadata(jar)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|since| since.as_millis())
+            .unwrap_or(0);
+        hasher.update(modified.to_le_bytes());
+    }
+    hasher.update([0]);
+    for account in accounts {
+        hasher.update(account.as_bytes());
+        hasher.update([0]);
+    }
+    hex::encode(&hasher.finalize()[..8])
+}
+
+fn fresh_info(db: &Database, url: &str, context: Option<&str>) -> Option<serde_json::Value> {
+    let settings = MediaCacheSettings::load(db);
+    if settings.ttl_secs == 0 {
+        return None;
+    }
+    let info = db
+        .get_cached_media_info(&crate::canonical::normalize_url(url), context, settings.fresh_after())
+        .ok()
+        .flatten()?;
+    serde_json::from_str(&info).ok()
+}
+
+/// The fresh cached yt-dlp JSON for `url` extracted in `context`, if any
+pub fn lookup(app_handle: &AppHandle, url: &str, context: &str) -> Option<serde_json::Value> {
+    let state = app_handle.try_state::<AppState>()?;
+    let db = state.db.lock().ok()?;
+    fresh_info(&db, url, Some(context))
+}
+
+/// The newest fresh cached JSON for `url` from any context, for callers that only
+/// read fields every context agrees on, such as the extractor and media id
+pub fn lookup_in(db: &Database, url: &str) -> Option<serde_json::Value> {
+    fresh_info(db, url, None)
+}
+
+// Per-request values yt-dlp copies into the info; they include the configured
+// headers and the jar's cookies, and yt-dlp recomputes them when info is loaded
+const SECRET_FIELDS: [&str; 2] = ["http_headers", "cookies"];
+
+/// The info without the headers and cookies yt-dlp attaches to it and its formats
+fn without_secrets(info: &serde_json::Value) -> serde_json::Value {
+    let mut info = info.clone();
+    let strip = |value: &mut serde_json::Value| {
+        if let Some(object) = value.as_object_mut() {
+            for field in SECRET_FIELDS {
+                object.remove(field);
+            }
+        }
+    };
+    strip(&mut info);
+    for list in ["formats", "requested_formats", "requested_downloads"] {
+        if let Some(entries) = info[list].as_array_mut() {
+            entries.iter_mut().for_each(strip);
+        }
+    }
+    info
+}
+
+/// Stores freshly extracted info, dropping entries that have gone stale meanwhile
+pub fn store(app_handle: &AppHandle, url: &str, context: &str, info: &serde_json::Value) {
+    let Some(state) = app_handle.try_state::<AppState>() else {
+        return;
+    };
+    let Ok(db) = state.db.lock() else {
+        return;
+    };
+    store_in(&db, url, context, info);
+}
+
+fn store_in(db: &Database, url: &str, context: &str, info: &serde_json::Value) {
+    let settings = MediaCacheSettings::load(db);
+    if settings.ttl_secs == 0 {
+        return;
+    }
+
+    let info = without_secrets(info);
+    let title = info["title"].as_str();
+    let result = db
+        .prune_media_cache(settings.fresh_after())
+        .and_then(|_| db.save_cached_media_info(&crate::canonical::normalize_url(url), context, title, &info.to_string()));
+    if let Err(e) = result {
+        println!("[MediaCache] Failed to cache info for {}: {}", url, e);
+    }
+}
+
+/// Drops the entries for `url`, e.g. when a download from cached info failed
+pub fn invalidate(app_handle: &AppHandle, url: &str) {
+    if let Some(state) = app_handle.try_state::<AppState>() {
+        if let Ok(db) = state.db.lock() {
+            let _ = db.delete_cached_media_info(&crate::canonical::normalize_url(url));
+        }
+    }
+}
+
+fn info_dir(app_handle: &AppHandle) -> Option<PathBuf> {
+    app_handle.path().app_data_dir().ok().map(|dir| dir.join("info-cache"))
+}
+
+/// Removes info files left behind if the app exited while yt-dlp was running
+pub fn clear_info_files(app_handle: &AppHandle) {
+    if let Some(dir) = info_dir(app_handle) {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
+
+/// Cached info written out for `--load-info-json`; the file is removed when dropped
+pub struct InfoJsonFile {
+    path: PathBuf,
+}
+
+impl InfoJsonFile {
+    /// Writes the fresh cached info for `url` in `context` to a file, or `None` if there is none.
+    ///
+    /// Info is extracted with `--no-playlist`, so it never stands in for a playlist URL.
+    pub fn for_url(app_handle: &AppHandle, url: &str, context: &str) -> Option<Self> {
+        if is_playlist_url(url) {
+            return None;
+        }
+        let info = lookup(app_handle, url, context)?;
+        let dir = info_dir(app_handle)?;
+        std::fs::create_dir_all(&dir).ok()?;
+        let path = dir.join(format!("{}.info.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, info.to_string()).ok()?;
+        Some(Self { path })
+    }
+
+    pub fn args(&self) -> Vec<String> {
+        vec!["--load-info-json".to_string(), self.path.to_string_lossy().to_string()]
+    }
+}
+
+impl Drop for InfoJsonFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+// Tauri commands for the media info cache
+#[tauri::command]
+pub async fn get_media_cache_settings(state: State<'_, AppState>) -> Result<MediaCacheSettings, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    Ok(MediaCacheSettings::load(&db))
+}
+
+#[tauri::command]
+pub async fn set_media_cache_settings(state: State<'_, AppState>, settings: MediaCacheSettings) -> Result<(), String> {
+    settings.validate()?;
+    let serialized = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.save_setting(MEDIA_CACHE_KEY, &serialized).map_err(|e| e.to_string())
+}
+
+/// Every cached entry, newest first, including stale ones that have not been pruned yet
+#[tauri::command]
+pub async fn get_media_cache(state: State<'_, AppState>) -> Result<Vec<MediaCacheEntry>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let settings = MediaCacheSettings::load(&db);
+    let mut entries = db.get_media_cache_entries().map_err(|e| e.to_string())?;
+    for entry in &mut entries {
+        entry.expires_at = entry.fetched_at + (settings.ttl_secs as i64) * 1000;
+    }
+    Ok(entries)
+}
+
+/// Removes the entry for `url`, or the whole cache; returns how many entries were removed
+#[tauri::command]
+pub async fn purge_media_cache(state: State<'_, AppState>, url: Option<String>) -> Result<usize, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    match url {
+        Some(url) => db.delete_cached_media_info(&crate::canonical::normalize_url(&url)),
+        None => db.clear_media_cache(),
+    }
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_info_has_no_headers_or_cookies() {
+        let dir = std::env::temp_dir().join(format!("omni-media-cache-{}", uuid::Uuid::new_v4()));
+        let db = Database::new(dir.clone()).unwrap();
+        let headers = serde_json::json!({ "Authorization": "Bearer s3cr3t-token", "User-Agent": "TestAgent" });
+        let format = serde_json::json!({
+            "format_id": "18",
+            "url": "https://media.example/18.mp4",
+            "http_headers": headers,
+            "cookies": "session=c00kie-value; Domain=.example.com",
+        });
+        let info = serde_json::json!({
+            "id": "abc",
+            "title": "Video",
+            "http_headers": headers,
+            "formats": [format],
+            "requested_formats": [format],
+            "requested_downloads": [format],
+        });
+
+        store_in(&db, "https://example.com/watch/abc", "context", &info);
+        let stored = db
+            .get_cached_media_info(&crate::canonical::normalize_url("https://example.com/watch/abc"), Some("context"), 0)
+            .unwrap()
+            .unwrap();
+        for secret in ["s3cr3t-token", "c00kie-value", "TestAgent"] {
+            assert!(!stored.contains(secret), "cached info contains {}", secret);
+        }
+        assert!(stored.contains("https://media.example/18.mp4"));
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,539 @@
+use crate::database::{DatabaseError, DbResult};
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+
+/// One schema change, applied in its own transaction together with the version bump.
+///
+/// Databases created before versioning report version 0 but may already contain any
+/// of the early steps, so steps up to `CANONICAL_IDS` must tolerate existing tables
+/// and columns. Later steps run exactly once and can rename or transform freely.
+struct Migration {
+    version: i64,
+    description: &'static str,
+    up: fn(&Connection) -> DbResult<()>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, description: "download history, search history and settings", up: history_tables },
+    Migration { version: 2, description: "download queue and attempt log", up: download_queue },
+    Migration { version: 3, description: "subtitle files", up: download_subtitles },
+    Migration { version: 4, description: "chapter files linked to their download", up: chapter_parents },
+    Migration { version: 5, description: "clip ranges", up: clip_ranges },
+    Migration { version: 6, description: "credential store", up: credential_store },
+    Migration { version: 7, description: "media info cache", up: media_info_cache },
+    Migration { version: CANONICAL_IDS, description: "canonical media ids", up: canonical_ids },
+    Migration { version: 9, description: "media cache context", up: media_cache_context },
+];
+
+// The last step that existed before schema versioning
+const CANONICAL_IDS: i64 = 8;
+
+/// The schema version this build creates and expects
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|migration| migration.version).unwrap_or(0)
+}
+
+pub fn user_version(conn: &Connection) -> DbResult<i64> {
+    Ok(conn.query_row("PRAGMA user_version", [], |row| row.get(0))?)
+}
+
+/// Where the copy of `db_path` taken before migrating from `version` is kept
+pub fn backup_path(db_path: &Path, version: i64) -> PathBuf {
+    let name = db_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "db.sqlite".to_string());
+    db_path.with_file_name(format!("{}.v{}.bak", name, version))
+}
+
+/// Brings the database at `db_path` up to the latest schema.
+///
+/// Existing databases are copied aside first, so a failed upgrade can be recovered by hand.
+pub fn run(conn: &Connection, db_path: &Path) -> DbResult<()> {
+    let version = user_version(conn)?;
+    let latest = latest_version();
+    if version > latest {
+        return Err(DatabaseError::Migration(format!(
+            "Database schema version {} is newer than this app supports ({})",
+            version, latest
+        )));
+    }
+    if version == latest {
+        return Ok(());
+    }
+
+    let has_tables: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table')",
+        [],
+        |row| row.get(0),
+    )?;
+    if has_tables && db_path.exists() {
+        let backup = backup_path(db_path, version);
+        std::fs::copy(db_path, &backup)?;
+        println!("[Database] Backed up schema version {} to {:?}", version, backup);
+    }
+
+    apply(conn, MIGRATIONS)
+}
+
+/// Applies every step newer than the current version, stopping at the first failure
+fn apply(conn: &Connection, migrations: &[Migration]) -> DbResult<()> {
+    let current = user_version(conn)?;
+    for migration in migrations.iter().filter(|migration| migration.version > current) {
+        let tx = conn.unchecked_transaction()?;
+        (migration.up)(&tx).map_err(|e| {
+            DatabaseError::Migration(format!(
+                "Step {} ({}) failed: {}",
+                migration.version, migration.description, e
+            ))
+        })?;
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+        println!("[Database] Migrated to schema version {}: {}", migration.version, migration.description);
+    }
+    Ok(())
+}
+
+/// Adds a column unless an unversioned database already has it
+fn add_column(conn: &Connection, table: &str, column: &str, definition: &str) -> DbResult<()> {
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2)",
+        [table, column],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    }
+    Ok(())
+}
+
+fn history_tables(conn: &Connection) -> DbResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS downloads (
+            id TEXT PRIMARY KEY,
+            title TEXT NOT NULL,
+            url TEXT NOT NULL,
+            format TEXT NOT NULL,
+            path TEXT NOT NULL,
+            timestamp INTEGER NOT NULL,
+            status TEXT NOT NULL,
+            size_bytes INTEGER,
+            platform TEXT,
+            thumbnail TEXT
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS search_history (
+            id TEXT PRIMARY KEY,
+            query TEXT NOT NULL,
+            timestamp INTEGER NOT NULL,
+            title TEXT,
+            thumbnail TEXT
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // The earliest releases had neither thumbnails nor search result titles
+    add_column(conn, "downloads", "thumbnail", "TEXT")?;
+    add_column(conn, "search_history", "title", "TEXT")?;
+    add_column(conn, "search_history", "thumbnail", "TEXT")?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_downloads_timestamp ON downloads(timestamp DESC)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_search_history_timestamp ON search_history(timestamp DESC)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn download_queue(conn: &Connection) -> DbResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS download_queue (
+            id TEXT PRIMARY KEY,
+            request TEXT NOT NULL,
+            state TEXT NOT NULL,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            last_error TEXT,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS download_attempts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            download_id TEXT NOT NULL,
+            attempt INTEGER NOT NULL,
+            started_at INTEGER NOT NULL,
+            finished_at INTEGER,
+            outcome TEXT,
+            reason TEXT
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_download_queue_state ON download_queue(state, created_at)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_download_attempts_download ON download_attempts(download_id)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn download_subtitles(conn: &Connection) -> DbResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS download_subtitles (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            download_id TEXT NOT NULL,
+            language TEXT NOT NULL,
+            format TEXT NOT NULL,
+            path TEXT,
+            embedded INTEGER NOT NULL DEFAULT 0,
+            created_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_download_subtitles_download ON download_subtitles(download_id)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn chapter_parents(conn: &Connection) -> DbResult<()> {
+    add_column(conn, "downloads", "parent_id", "TEXT")?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_downloads_parent ON downloads(parent_id)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn clip_ranges(conn: &Connection) -> DbResult<()> {
+    add_column(conn, "downloads", "clip", "TEXT")
+}
+
+fn credential_store(conn: &Connection) -> DbResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS credential_vault (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            salt BLOB NOT NULL,
+            nonce BLOB NOT NULL,
+            verifier BLOB NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS credentials (
+            machine TEXT PRIMARY KEY,
+            nonce BLOB NOT NULL,
+            secret BLOB NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn media_info_cache(conn: &Connection) -> DbResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS media_info_cache (
+            url TEXT PRIMARY KEY,
+            title TEXT,
+            info TEXT NOT NULL,
+            fetched_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Existing rows are given ids by `Database::backfill_canonical_ids` at startup
+fn canonical_ids(conn: &Connection) -> DbResult<()> {
+    add_column(conn, "downloads", "canonical_id", "TEXT")?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_downloads_canonical ON downloads(canonical_id, status)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Keys cached info by the network, cookie and credential context it was extracted in.
+/// The cache only saves a page fetch, so existing entries are dropped rather than converted
+fn media_cache_context(conn: &Connection) -> DbResult<()> {
+    conn.execute_batch(
+        "DROP TABLE IF EXISTS media_info_cache;
+         CREATE TABLE media_info_cache (
+            url TEXT NOT NULL,
+            context TEXT NOT NULL,
+            title TEXT,
+            info TEXT NOT NULL,
+            fetched_at INTEGER NOT NULL,
+            PRIMARY KEY (url, context)
+         );",
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Schemas as shipped before versioning, oldest first; each is the previous plus one change
+
+    const PRE_THUMBNAILS: &str = "
+        CREATE TABLE downloads (
+            id TEXT PRIMARY KEY, title TEXT NOT NULL, url TEXT NOT NULL, format TEXT NOT NULL,
+            path TEXT NOT NULL, timestamp INTEGER NOT NULL, status TEXT NOT NULL,
+            size_bytes INTEGER, platform TEXT
+        );
+        CREATE TABLE search_history (id TEXT PRIMARY KEY, query TEXT NOT NULL, timestamp INTEGER NOT NULL);
+        CREATE TABLE settings (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+        CREATE INDEX idx_downloads_timestamp ON downloads(timestamp DESC);
+        CREATE INDEX idx_search_history_timestamp ON search_history(timestamp DESC);
+    ";
+
+    const THUMBNAILS: &str = "
+        ALTER TABLE downloads ADD COLUMN thumbnail TEXT;
+        ALTER TABLE search_history ADD COLUMN title TEXT;
+        ALTER TABLE search_history ADD COLUMN thumbnail TEXT;
+    ";
+
+    const QUEUE: &str = "
+        CREATE TABLE download_queue (
+            id TEXT PRIMARY KEY, request TEXT NOT NULL, state TEXT NOT NULL,
+            attempts INTEGER NOT NULL DEFAULT 0, last_error TEXT,
+            created_at INTEGER NOT NULL, updated_at INTEGER NOT NULL
+        );
+        CREATE INDEX idx_download_queue_state ON download_queue(state, created_at);
+    ";
+
+    const ATTEMPTS: &str = "
+        CREATE TABLE download_attempts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT, download_id TEXT NOT NULL, attempt INTEGER NOT NULL,
+            started_at INTEGER NOT NULL, finished_at INTEGER, outcome TEXT, reason TEXT
+        );
+        CREATE INDEX idx_download_attempts_download ON download_attempts(download_id);
+    ";
+
+    const SUBTITLES: &str = "
+        CREATE TABLE download_subtitles (
+            id INTEGER PRIMARY KEY AUTOINCREMENT, download_id TEXT NOT NULL, language TEXT NOT NULL,
+            format TEXT NOT NULL, path TEXT, embedded INTEGER NOT NULL DEFAULT 0, created_at INTEGER NOT NULL
+        );
+        CREATE INDEX idx_download_subtitles_download ON download_subtitles(download_id);
+    ";
+
+    const PARENTS: &str = "
+        ALTER TABLE downloads ADD COLUMN parent_id TEXT;
+        CREATE INDEX idx_downloads_parent ON downloads(parent_id);
+    ";
+
+    const CLIPS: &str = "ALTER TABLE downloads ADD COLUMN clip TEXT;";
+
+    const CREDENTIALS: &str = "
+        CREATE TABLE credential_vault (
+            id INTEGER PRIMARY KEY CHECK (id = 1), salt BLOB NOT NULL, nonce BLOB NOT NULL, verifier BLOB NOT NULL
+        );
+        CREATE TABLE credentials (
+            machine TEXT PRIMARY KEY, nonce BLOB NOT NULL, secret BLOB NOT NULL, updated_at INTEGER NOT NULL
+        );
+    ";
+
+    const MEDIA_CACHE: &str = "
+        CREATE TABLE media_info_cache (
+            url TEXT PRIMARY KEY, title TEXT, info TEXT NOT NULL, fetched_at INTEGER NOT NULL
+        );
+    ";
+
+    const CANONICAL: &str = "
+        ALTER TABLE downloads ADD COLUMN canonical_id TEXT;
+        CREATE INDEX idx_downloads_canonical ON downloads(canonical_id, status);
+    ";
+
+    const HISTORY: [&str; 10] = [
+        PRE_THUMBNAILS, THUMBNAILS, QUEUE, ATTEMPTS, SUBTITLES, PARENTS, CLIPS, CREDENTIALS, MEDIA_CACHE, CANONICAL,
+    ];
+
+    /// An unversioned database with the first `steps` historical changes and one download
+    fn legacy_database(steps: usize) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        for sql in &HISTORY[..steps] {
+            conn.execute_batch(sql).unwrap();
+        }
+        conn.execute(
+            "INSERT INTO downloads (id, title, url, format, path, timestamp, status)
+             VALUES ('d1', 'Old video', 'https://youtu.be/abc', 'best', '/tmp/old.mp4', 1, 'completed')",
+            [],
+        )
+        .unwrap();
+        conn.execute("INSERT INTO settings (key, value) VALUES ('theme', 'dark')", []).unwrap();
+        conn
+    }
+
+    /// Tables with their columns, and index definitions, in a comparable form
+    fn schema(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT m.type, m.name, p.name, p.type, p.\"notnull\", p.pk
+                 FROM sqlite_master m LEFT JOIN pragma_table_info(m.name) p
+                 WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite_%'
+                 UNION ALL
+                 SELECT type, name, tbl_name, '', 0, 0 FROM sqlite_master WHERE type = 'index' AND sql IS NOT NULL
+                 ORDER BY 1, 2, 3",
+            )
+            .unwrap();
+        stmt.query_map([], |row| {
+            Ok(format!(
+                "{} {} {} {} {} {}",
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                row.get::<_, Option<i64>>(4)?.unwrap_or_default(),
+                row.get::<_, Option<i64>>(5)?.unwrap_or_default(),
+            ))
+        })
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap()
+    }
+
+    fn fresh_schema() -> Vec<String> {
+        let conn = Connection::open_in_memory().unwrap();
+        apply(&conn, MIGRATIONS).unwrap();
+        schema(&conn)
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("omni-migrations-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn fresh_database_reaches_latest_version() {
+        let conn = Connection::open_in_memory().unwrap();
+        apply(&conn, MIGRATIONS).unwrap();
+        assert_eq!(user_version(&conn).unwrap(), latest_version());
+        assert!(schema(&conn).iter().any(|line| line.starts_with("table downloads canonical_id")));
+    }
+
+    #[test]
+    fn upgrades_every_historical_schema() {
+        let expected = fresh_schema();
+        for steps in 1..=HISTORY.len() {
+            let conn = legacy_database(steps);
+            apply(&conn, MIGRATIONS).unwrap_or_else(|e| panic!("schema {} failed: {}", steps, e));
+
+            assert_eq!(user_version(&conn).unwrap(), latest_version(), "schema {}", steps);
+            assert_eq!(schema(&conn), expected, "schema {}", steps);
+            let title: String = conn
+                .query_row("SELECT title FROM downloads WHERE id = 'd1'", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(title, "Old video", "schema {}", steps);
+            let theme: String = conn
+                .query_row("SELECT value FROM settings WHERE key = 'theme'", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(theme, "dark", "schema {}", steps);
+        }
+    }
+
+    #[test]
+    fn migrating_again_changes_nothing() {
+        let conn = Connection::open_in_memory().unwrap();
+        apply(&conn, MIGRATIONS).unwrap();
+        let before = schema(&conn);
+        apply(&conn, MIGRATIONS).unwrap();
+        assert_eq!(schema(&conn), before);
+        assert_eq!(user_version(&conn).unwrap(), latest_version());
+    }
+
+    #[test]
+    fn failed_step_is_rolled_back() {
+        fn create_table(conn: &Connection) -> DbResult<()> {
+            conn.execute("CREATE TABLE partial (id INTEGER)", [])?;
+            Ok(())
+        }
+        fn broken(conn: &Connection) -> DbResult<()> {
+            conn.execute("CREATE TABLE half_done (id INTEGER)", [])?;
+            conn.execute("ALTER TABLE missing ADD COLUMN value TEXT", [])?;
+            Ok(())
+        }
+        let steps = [
+            Migration { version: 1, description: "works", up: create_table },
+            Migration { version: 2, description: "fails", up: broken },
+        ];
+
+        let conn = Connection::open_in_memory().unwrap();
+        let error = apply(&conn, &steps).unwrap_err();
+        assert!(error.to_string().contains("Step 2 (fails)"));
+        assert_eq!(user_version(&conn).unwrap(), 1);
+        let tables: Vec<String> = schema(&conn).into_iter().map(|line| line.split(' ').nth(1).unwrap().to_string()).collect();
+        assert!(tables.contains(&"partial".to_string()));
+        assert!(!tables.contains(&"half_done".to_string()));
+    }
+
+    #[test]
+    fn refuses_newer_database() {
+        let dir = temp_dir();
+        let path = dir.join("db.sqlite");
+        let conn = Connection::open(&path).unwrap();
+        conn.pragma_update(None, "user_version", latest_version() + 1).unwrap();
+
+        assert!(matches!(run(&conn, &path), Err(DatabaseError::Migration(_))));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn backs_up_legacy_database_before_migrating() {
+        let dir = temp_dir();
+        let path = dir.join("db.sqlite");
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(PRE_THUMBNAILS).unwrap();
+        conn.execute("INSERT INTO settings (key, value) VALUES ('theme', 'dark')", []).unwrap();
+
+        run(&conn, &path).unwrap();
+        assert_eq!(user_version(&conn).unwrap(), latest_version());
+
+        let backup = Connection::open(backup_path(&path, 0)).unwrap();
+        assert_eq!(user_version(&backup).unwrap(), 0);
+        let has_thumbnail: bool = backup
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM pragma_table_info('downloads') WHERE name = 'thumbnail')",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(!has_thumbnail);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn new_database_is_not_backed_up() {
+        let dir = temp_dir();
+        let path = dir.join("db.sqlite");
+        let conn = Connection::open(&path).unwrap();
+
+        run(&conn, &path).unwrap();
+        assert!(!backup_path(&path, 0).exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
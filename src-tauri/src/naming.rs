@@ -0,0 +1,550 @@
+use crate::commands::AppState;
+use crate::database::Database;
+use crate::downloader::{CompletedDownload, DownloadRequest, Downloader, FormatInfo, MediaInfo};
+use crate::error::DownloadError;
+use crate::queue::{STATE_PAUSED, STATE_QUEUED, STATE_RETRYING, STATE_RUNNING};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+
+// Settings key the naming settings are persisted under
+const NAMING_KEY: &str = "naming";
+
+pub const DEFAULT_TEMPLATE: &str = "{title}";
+
+/// Fields a naming template may reference as `{field}`
+pub const TEMPLATE_FIELDS: [&str; 8] = [
+    "title",
+    "id",
+    "uploader",
+    "upload_date",
+    "playlist",
+    "playlist_index",
+    "resolution",
+    "platform",
+];
+
+// Leaves room for a collision suffix and the extension within the usual 255 byte limit
+const MAX_SEGMENT_BYTES: usize = 200;
+
+// Device names Windows refuses as file names, with or without an extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// What to do when the rendered file name is already taken
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionPolicy {
+    /// Keep the existing file and mark the download as completed
+    Skip,
+    Overwrite,
+    /// Add " (1)", " (2)", ... until the name is free
+    #[default]
+    AppendSuffix,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NamingSettings {
+    /// Path relative to the output folder, without extension; `/` creates subfolders
+    pub template: String,
+    pub collision_policy: CollisionPolicy,
+}
+
+impl Default for NamingSettings {
+    fn default() -> Self {
+        Self {
+            template: DEFAULT_TEMPLATE.to_string(),
+            collision_policy: CollisionPolicy::default(),
+        }
+    }
+}
+
+impl NamingSettings {
+    pub fn load(db: &Database) -> Self {
+        db.get_setting(NAMING_KEY)
+            .ok()
+            .flatten()
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Values a template is rendered against
+#[derive(Debug, Clone, Default)]
+pub struct TemplateContext {
+    pub title: Option<String>,
+    pub id: Option<String>,
+    pub uploader: Option<String>,
+    pub upload_date: Option<String>,
+    pub playlist: Option<String>,
+    pub playlist_index: Option<usize>,
+    pub resolution: Option<String>,
+    pub platform: Option<String>,
+}
+
+impl TemplateContext {
+    pub fn from_media(info: &MediaInfo, playlist: Option<&str>, playlist_index: Option<usize>) -> Self {
+        Self {
+            title: Some(info.title.clone()),
+            id: info.id.clone(),
+            uploader: info.uploader.clone(),
+            upload_date: info.upload_date.clone(),
+            playlist: playlist.map(|s| s.to_string()),
+            playlist_index,
+            resolution: info.resolution.clone(),
+            platform: Some(info.platform.clone()),
+        }
+    }
+
+    /// The values a request already carries, so planning only extracts info when
+    /// the template needs more
+    pub fn from_request(request: &DownloadRequest) -> Self {
+        let id = crate::canonical::known_id(&request.url)
+            .and_then(|known| known.split_once(':').map(|(_, id)| id.to_string()));
+        Self {
+            title: request.title.clone(),
+            id,
+            playlist: request.playlist_title.clone(),
+            playlist_index: request.playlist_index,
+            resolution: request.audio_only.then(|| "audio".to_string()),
+            platform: request.platform.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Fills the fields the request did not carry from extracted info
+    fn fill_from(&mut self, info: &MediaInfo, request: &DownloadRequest) {
+        self.title.get_or_insert_with(|| info.title.clone());
+        self.id = self.id.take().or_else(|| info.id.clone());
+        self.uploader = self.uploader.take().or_else(|| info.uploader.clone());
+        self.upload_date = self.upload_date.take().or_else(|| info.upload_date.clone());
+        self.resolution = self.resolution.take().or_else(|| selected_resolution(request, info));
+        self.platform.get_or_insert_with(|| info.platform.clone());
+    }
+
+    fn field(&self, name: &str) -> Option<String> {
+        match name {
+            "title" => self.title.clone(),
+            "id" => self.id.clone(),
+            "uploader" => self.uploader.clone(),
+            "upload_date" => self.upload_date.clone(),
+            "playlist" => self.playlist.clone(),
+            "playlist_index" => self.playlist_index.map(|i| i.to_string()),
+            "resolution" => self.resolution.clone(),
+            "platform" => self.platform.clone(),
+            _ => None,
+        }
+    }
+}
+
+/// One piece of a parsed template segment
+enum Token<'a> {
+    Literal(&'a str),
+    Field(&'a str),
+}
+
+fn tokenize(segment: &str) -> Result<Vec<Token<'_>>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = segment;
+    while !rest.is_empty() {
+        match rest.find(['{', '}']) {
+            Some(pos) if rest[pos..].starts_with('}') => {
+                return Err("Unmatched '}' in template".to_string());
+            }
+            Some(pos) => {
+                if pos > 0 {
+                    tokens.push(Token::Literal(&rest[..pos]));
+                }
+                let end = rest[pos..]
+                    .find('}')
+                    .ok_or_else(|| "Unclosed '{' in template".to_string())?;
+                let name = rest[pos + 1..pos + end].trim();
+                if !TEMPLATE_FIELDS.contains(&name) {
+                    return Err(format!(
+                        "Unknown template field '{}'. Available: {}",
+                        name,
+                        TEMPLATE_FIELDS.join(", ")
+                    ));
+                }
+                tokens.push(Token::Field(name));
+                rest = &rest[pos + end + 1..];
+            }
+            None => {
+                tokens.push(Token::Literal(rest));
+                rest = "";
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Resolution of the format the request selects, rather than the best one available
+fn selected_resolution(request: &DownloadRequest, info: &MediaInfo) -> Option<String> {
+    let is_video = |format: &&FormatInfo| format.vcodec.as_deref().is_some_and(|codec| codec != "none");
+    let height = |format: &FormatInfo| {
+        let resolution = format.resolution.as_deref()?;
+        resolution.rsplit('x').next()?.parse::<u32>().ok()
+    };
+
+    if let Some(selector) = request.format.as_deref().filter(|format| !format.is_empty()) {
+        // "137+140/best" takes its video from the first id that names a video format
+        return selector
+            .split(['+', '/'])
+            .find_map(|id| info.formats.iter().filter(is_video).find(|format| format.format_id == id))
+            .and_then(|format| format.resolution.clone());
+    }
+
+    // Caps match the format selectors built for each quality in run_download
+    let cap = match request.quality.as_deref() {
+        Some("1080p") => Some(1080),
+        Some("720p") => Some(720),
+        Some("480p") => Some(480),
+        Some("360p") => Some(360),
+        _ => None,
+    };
+    info.formats
+        .iter()
+        .filter(is_video)
+        .filter_map(|format| Some((height(format)?, format)))
+        .filter(|(height, _)| cap.is_none_or(|cap| *height <= cap))
+        .max_by_key(|(height, _)| *height)
+        .and_then(|(_, format)| format.resolution.clone())
+        .or_else(|| info.resolution.clone())
+}
+
+/// The fields a valid template references
+fn template_fields(template: &str) -> Vec<&str> {
+    segments(template)
+        .filter_map(|segment| tokenize(segment).ok())
+        .flatten()
+        .filter_map(|token| match token {
+            Token::Field(name) => Some(name),
+            Token::Literal(_) => None,
+        })
+        .collect()
+}
+
+fn segments(template: &str) -> impl Iterator<Item = &str> {
+    template.split(['/', '\\']).filter(|s| !s.trim().is_empty())
+}
+
+pub fn validate_template(template: &str) -> Result<(), String> {
+    if segments(template).next().is_none() {
+        return Err("Template must not be empty".to_string());
+    }
+    for segment in segments(template) {
+        tokenize(segment)?;
+    }
+    Ok(())
+}
+
+/// Renders a template to a relative path, sanitizing every path segment.
+///
+/// Missing fields render as `NA`, matching yt-dlp's own output templates.
+pub fn render_template(template: &str, context: &TemplateContext) -> Result<PathBuf, String> {
+    validate_template(template)?;
+    let mut path = PathBuf::new();
+    for segment in segments(template) {
+        let rendered: String = tokenize(segment)?
+            .into_iter()
+            .map(|token| match token {
+                Token::Literal(text) => text.to_string(),
+                Token::Field(name) => context.field(name).unwrap_or_else(|| "NA".to_string()),
+            })
+            .collect();
+        path.push(sanitize_filename(&rendered));
+    }
+    Ok(path)
+}
+
+/// Replaces characters that are not allowed in file or folder names on any platform
+pub fn sanitize_filename(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    // Windows rejects names ending in a dot or space
+    let mut trimmed = truncate_bytes(cleaned.trim(), MAX_SEGMENT_BYTES)
+        .trim_end_matches(['.', ' '])
+        .to_string();
+    if trimmed.is_empty() {
+        return "Untitled".to_string();
+    }
+
+    let stem = trimmed.split('.').next().unwrap_or("").trim_end();
+    if RESERVED_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem)) {
+        trimmed.insert(0, '_');
+    }
+    trimmed
+}
+
+fn truncate_bytes(value: &str, max: usize) -> &str {
+    if value.len() <= max {
+        return value;
+    }
+    let mut end = max;
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    &value[..end]
+}
+
+// Side files written next to the media, which don't make the name taken on their own
+const SIDECAR_EXTENSIONS: [&str; 6] = ["srt", "vtt", "ass", "lrc", "json", "description"];
+// Suffixes yt-dlp adds to files it is still writing
+const PARTIAL_SUFFIXES: [&str; 2] = [".part", ".ytdl"];
+// Queue states whose jobs own their output name
+const ACTIVE_STATES: [&str; 4] = [STATE_QUEUED, STATE_RUNNING, STATE_PAUSED, STATE_RETRYING];
+
+/// The files in the stem's folder, each split into the stem yt-dlp wrote it for and whether it
+/// is still being written: "Title.mp4", "Title.mp4.part", "Title.f137.mp4.part-Frag3" all
+/// belong to "Title"
+fn files_for(stem: &Path) -> Vec<(PathBuf, bool)> {
+    let (Some(dir), Some(name)) = (stem.parent(), stem.file_name()) else {
+        return Vec::new();
+    };
+    let name = name.to_string_lossy().to_string();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter_map(|path| {
+            let file_name = path.file_name()?.to_string_lossy().to_string();
+            let (media, partial) = match file_name.find(".part-Frag") {
+                Some(index) => (&file_name[..index], true),
+                None => PARTIAL_SUFFIXES
+                    .iter()
+                    .find_map(|suffix| file_name.strip_suffix(suffix))
+                    .map_or((file_name.as_str(), false), |media| (media, true)),
+            };
+            let (file_stem, extension) = media.rsplit_once('.')?;
+            if SIDECAR_EXTENSIONS.contains(&extension.to_lowercase().as_str()) {
+                return None;
+            }
+            // Separate video and audio streams are written as "Title.f137.mp4" before merging
+            let stream_of = file_stem
+                .rsplit_once(".f")
+                .filter(|(_, format)| partial && format.chars().next().is_some_and(|c| c.is_ascii_digit()))
+                .map(|(base, _)| base);
+            (file_stem == name || stream_of == Some(name.as_str())).then_some((path, partial))
+        })
+        .collect()
+}
+
+/// A finished file with exactly this stem; partial downloads and side files don't count
+fn find_existing(stem: &Path) -> Option<PathBuf> {
+    files_for(stem)
+        .into_iter()
+        .find(|(_, partial)| !partial)
+        .map(|(path, _)| path)
+}
+
+/// Whether another download is already writing to this stem, or has been given it
+fn is_claimed(stem: &Path, reserved: &HashSet<PathBuf>) -> bool {
+    reserved.contains(stem) || files_for(stem).iter().any(|(_, partial)| *partial)
+}
+
+/// Output stems fixed by other queued, running or paused jobs
+fn reserved_stems(db: &Database, own_id: &str) -> HashSet<PathBuf> {
+    db.get_queue()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|entry| entry.id != own_id && ACTIVE_STATES.contains(&entry.state.as_str()))
+        .filter_map(|entry| serde_json::from_str::<DownloadRequest>(&entry.request).ok())
+        .filter_map(|request| Some(PathBuf::from(&request.output_path).join(request.output_name?)))
+        .collect()
+}
+
+/// Where a download should be written, decided before yt-dlp is started
+pub enum OutputPlan {
+    /// Download to `stem` plus yt-dlp's extension; `overwrite` replaces a file already at that name
+    Download { stem: PathBuf, overwrite: bool },
+    /// The skip policy found a finished file at the target name
+    Existing(CompletedDownload),
+}
+
+/// Works out the output file for a request, fixing the name on first run so
+/// retries and resumes keep writing to the same `.part` files.
+pub async fn plan_output(
+    app: &AppHandle,
+    downloader: &Downloader,
+    request: &mut DownloadRequest,
+) -> Result<OutputPlan, DownloadError> {
+    let output_dir = PathBuf::from(&request.output_path);
+
+    if let Some(name) = &request.output_name {
+        return Ok(OutputPlan::Download { stem: output_dir.join(name), overwrite: false });
+    }
+
+    let settings = {
+        let state = app.state::<AppState>();
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        NamingSettings::load(&db)
+    };
+    let template = request.filename_template.clone().unwrap_or(settings.template);
+    let policy = request.collision_policy.unwrap_or(settings.collision_policy);
+
+    // Extracting info costs a page fetch on top of yt-dlp's own, so it is only done
+    // when the template uses a field the request does not carry
+    let mut context = TemplateContext::from_request(request);
+    if template_fields(&template).iter().any(|name| context.field(name).is_none()) {
+        let info = downloader.get_media_info(&request.url, request.network.as_ref()).await?;
+        context.fill_from(&info, request);
+    }
+    let relative = render_template(&template, &context).map_err(DownloadError::invalid)?;
+
+    // Names are checked and claimed under the database lock, so downloads planned at the
+    // same time, such as playlist entries with equal titles, never get the same stem
+    let state = app.state::<AppState>();
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let reserved = reserved_stems(&db, &request.id);
+
+    let mut target = output_dir.join(&relative);
+    let mut overwrite = false;
+    let claimed = is_claimed(&target, &reserved);
+    match find_existing(&target) {
+        Some(existing) if !claimed && policy == CollisionPolicy::Skip => {
+            println!("[Naming] {} already exists, skipping", existing.display());
+            let size_bytes = std::fs::metadata(&existing).ok().map(|m| m.len() as i64);
+            return Ok(OutputPlan::Existing(CompletedDownload {
+                filepath: Some(existing.to_string_lossy().to_string()),
+                size_bytes,
+                ..Default::default()
+            }));
+        }
+        Some(_) if !claimed && policy == CollisionPolicy::Overwrite => overwrite = true,
+        // A name another download is using is never skipped or overwritten
+        existing if existing.is_some() || claimed => {
+            let base = target.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            let mut counter = 1;
+            while find_existing(&target).is_some() || is_claimed(&target, &reserved) {
+                target.set_file_name(format!("{} ({})", base, counter));
+                counter += 1;
+            }
+        }
+        _ => {}
+    }
+
+    let name = target
+        .strip_prefix(&output_dir)
+        .unwrap_or(&target)
+        .to_string_lossy()
+        .to_string();
+    request.output_name = Some(name);
+
+    // Persist the chosen name so a resume after restart reuses it, and so later plans see it
+    if let Ok(serialized) = serde_json::to_string(&request) {
+        let _ = db.update_job_request(&request.id, &serialized);
+    }
+
+    Ok(OutputPlan::Download { stem: target, overwrite })
+}
+
+/// A yt-dlp output template for a literal path, leaving the extension to yt-dlp
+pub fn output_template(stem: &Path) -> String {
+    format!("{}.%(ext)s", escape_template(stem))
+}
+
+/// Escapes a literal path for use inside a yt-dlp output template
+pub fn escape_template(path: &Path) -> String {
+    path.to_string_lossy().replace('%', "%%")
+}
+
+// Tauri commands for file naming
+#[tauri::command]
+pub async fn get_naming_settings(state: State<'_, AppState>) -> Result<NamingSettings, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    Ok(NamingSettings::load(&db))
+}
+
+#[tauri::command]
+pub async fn set_naming_settings(state: State<'_, AppState>, settings: NamingSettings) -> Result<(), String> {
+    validate_template(&settings.template)?;
+    let serialized = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.save_setting(NAMING_KEY, &serialized).map_err(|e| e.to_string())
+}
+
+/// Renders a template against media info so the settings page can show an example path
+#[tauri::command]
+pub async fn preview_filename_template(
+    template: String,
+    info: MediaInfo,
+    playlist_title: Option<String>,
+    playlist_index: Option<usize>,
+) -> Result<String, String> {
+    let context = TemplateContext::from_media(&info, playlist_title.as_deref(), playlist_index);
+    render_template(&template, &context).map(|path| path.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserved_windows_names_are_prefixed() {
+        for name in ["CON", "con", "Nul", "COM1", "lpt9", "aux.mp4", "PRN .txt"] {
+            assert!(sanitize_filename(name).starts_with('_'), "{}", name);
+        }
+        for name in ["CONSOLE", "COM10", "my con", "nul-video"] {
+            assert_eq!(sanitize_filename(name), name);
+        }
+    }
+
+    #[test]
+    fn invalid_characters_and_trailing_dots_are_replaced() {
+        assert_eq!(sanitize_filename("a/b\\c:d*e?f\"g<h>i|j"), "a_b_c_d_e_f_g_h_i_j");
+        assert_eq!(sanitize_filename("line\nbreak"), "line_break");
+        assert_eq!(sanitize_filename("ends with dots... "), "ends with dots");
+        assert_eq!(sanitize_filename(" ..  "), "Untitled");
+    }
+
+    #[test]
+    fn truncation_never_splits_a_character() {
+        for filler in ["é", "日本", "🎵", "a"] {
+            for offset in 0..4 {
+                let name = format!("{}{}", "x".repeat(offset), filler.repeat(MAX_SEGMENT_BYTES));
+                let sanitized = sanitize_filename(&name);
+                assert!(sanitized.len() <= MAX_SEGMENT_BYTES, "{} bytes", sanitized.len());
+                assert!(sanitized.len() > MAX_SEGMENT_BYTES - 4);
+                assert!(name.starts_with(&sanitized));
+            }
+        }
+    }
+
+    #[test]
+    fn templates_reject_unknown_and_unterminated_fields() {
+        assert!(validate_template("{title} [{id}]").is_ok());
+        assert!(validate_template("{ playlist }/{playlist_index} - {title}").is_ok());
+        assert!(validate_template("{titel}").unwrap_err().contains("Unknown template field 'titel'"));
+        assert!(validate_template("{}").unwrap_err().contains("Unknown template field ''"));
+        assert!(validate_template("{title").unwrap_err().contains("Unclosed"));
+        assert!(validate_template("title}").unwrap_err().contains("Unmatched"));
+        assert!(validate_template("{title}/{uploader").is_err());
+        assert!(validate_template(" / ").unwrap_err().contains("empty"));
+    }
+
+    #[test]
+    fn templates_render_missing_fields_as_na() {
+        let context = TemplateContext {
+            title: Some("A: B".to_string()),
+            playlist: Some("Mix/Tape".to_string()),
+            playlist_index: Some(3),
+            ..Default::default()
+        };
+        let path = render_template("{playlist}/{playlist_index} - {title} [{uploader}]", &context).unwrap();
+        assert_eq!(path, Path::new("Mix_Tape").join("3 - A_ B [NA]"));
+    }
+}
@@ -0,0 +1,226 @@
+use crate::commands::AppState;
+use crate::database::Database;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use tauri::State;
+
+// Settings key the network settings are persisted under
+pub(crate) const NETWORK_KEY: &str = "network";
+
+const PROXY_SCHEMES: [&str; 6] = ["http", "https", "socks4", "socks4a", "socks5", "socks5h"];
+const MAX_SOCKET_TIMEOUT_SECS: u64 = 600;
+// Headers that carry credentials rather than preferences
+const SECRET_HEADERS: [&str; 3] = ["authorization", "proxy-authorization", "cookie"];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IpVersion {
+    V4,
+    V6,
+}
+
+/// Network options passed to every yt-dlp invocation.
+///
+/// The same shape is used for per-request overrides, where set fields replace
+/// the saved ones and headers are merged.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct NetworkSettings {
+    /// e.g. "socks5://127.0.0.1:1080"; an empty string forces a direct connection
+    pub proxy: Option<String>,
+    pub user_agent: Option<String>,
+    pub headers: BTreeMap<String, String>,
+    /// Local IP address to bind outgoing connections to
+    pub source_address: Option<String>,
+    pub ip_version: Option<IpVersion>,
+    pub socket_timeout_secs: Option<u64>,
+}
+
+impl NetworkSettings {
+    pub fn load(db: &Database) -> Self {
+        db.get_setting(NETWORK_KEY)
+            .ok()
+            .flatten()
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(proxy) = self.proxy.as_deref().filter(|p| !p.is_empty()) {
+            validate_proxy(proxy)?;
+        }
+
+        if let Some(user_agent) = &self.user_agent {
+            if user_agent.trim().is_empty() || user_agent.contains(['\r', '\n']) {
+                return Err("User agent must be a single non-empty line".to_string());
+            }
+        }
+
+        for (name, value) in &self.headers {
+            let valid_name = !name.is_empty()
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c));
+            if !valid_name {
+                return Err(format!("Invalid header name '{}'", name));
+            }
+            if value.contains(['\r', '\n']) {
+                return Err(format!("Header '{}' must be a single line", name));
+            }
+        }
+
+        if let Some(address) = &self.source_address {
+            let ip: IpAddr = address
+                .trim()
+                .parse()
+                .map_err(|_| format!("Source address '{}' is not an IP address", address))?;
+            match (self.ip_version, ip) {
+                (Some(IpVersion::V4), IpAddr::V6(_)) | (Some(IpVersion::V6), IpAddr::V4(_)) => {
+                    return Err("Source address does not match the forced IP version".to_string());
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(timeout) = self.socket_timeout_secs {
+            if timeout == 0 || timeout > MAX_SOCKET_TIMEOUT_SECS {
+                return Err(format!(
+                    "Socket timeout must be between 1 and {} seconds",
+                    MAX_SOCKET_TIMEOUT_SECS
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Layers per-request overrides on top of these settings
+    pub fn merged(&self, overrides: &NetworkSettings) -> NetworkSettings {
+        let mut headers = self.headers.clone();
+        headers.extend(overrides.headers.clone());
+        NetworkSettings {
+            proxy: overrides.proxy.clone().or_else(|| self.proxy.clone()),
+            user_agent: overrides.user_agent.clone().or_else(|| self.user_agent.clone()),
+            headers,
+            source_address: overrides.source_address.clone().or_else(|| self.source_address.clone()),
+            ip_version: overrides.ip_version.or(self.ip_version),
+            socket_timeout_secs: overrides.socket_timeout_secs.or(self.socket_timeout_secs),
+        }
+    }
+
+    /// A copy without proxy credentials or credential headers, e.g. for export
+    pub fn without_secrets(&self) -> NetworkSettings {
+        let mut settings = self.clone();
+        settings.proxy = self.proxy.as_deref().map(strip_userinfo);
+        settings
+            .headers
+            .retain(|name, _| !SECRET_HEADERS.contains(&name.to_lowercase().as_str()));
+        settings
+    }
+
+    /// Puts back the credentials [`without_secrets`](Self::without_secrets) removed,
+    /// taking them from `local` where the proxy host is the same
+    pub fn with_secrets_from(&self, local: &NetworkSettings) -> NetworkSettings {
+        let mut settings = self.clone();
+        if let (Some(proxy), Some(local_proxy)) = (&self.proxy, &local.proxy) {
+            if *proxy == strip_userinfo(local_proxy) {
+                settings.proxy = Some(local_proxy.clone());
+            }
+        }
+        for (name, value) in &local.headers {
+            if SECRET_HEADERS.contains(&name.to_lowercase().as_str()) {
+                settings.headers.entry(name.clone()).or_insert_with(|| value.clone());
+            }
+        }
+        settings
+    }
+
+    pub fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(proxy) = &self.proxy {
+            args.extend(["--proxy".to_string(), proxy.trim().to_string()]);
+        }
+        if let Some(user_agent) = &self.user_agent {
+            args.extend(["--user-agent".to_string(), user_agent.trim().to_string()]);
+        }
+        for (name, value) in &self.headers {
+            args.extend(["--add-headers".to_string(), format!("{}:{}", name, value.trim())]);
+        }
+        if let Some(address) = &self.source_address {
+            args.extend(["--source-address".to_string(), address.trim().to_string()]);
+        }
+        match self.ip_version {
+            Some(IpVersion::V4) => args.push("--force-ipv4".to_string()),
+            Some(IpVersion::V6) => args.push("--force-ipv6".to_string()),
+            None => {}
+        }
+        if let Some(timeout) = self.socket_timeout_secs {
+            args.extend(["--socket-timeout".to_string(), timeout.to_string()]);
+        }
+        args
+    }
+}
+
+/// The proxy URL without a user:pass@ part
+fn strip_userinfo(proxy: &str) -> String {
+    let Some((scheme, rest)) = proxy.split_once("://") else {
+        return proxy.to_string();
+    };
+    let authority_end = rest.find('/').unwrap_or(rest.len());
+    match rest[..authority_end].rfind('@') {
+        Some(at) => format!("{}://{}", scheme, &rest[at + 1..]),
+        None => proxy.to_string(),
+    }
+}
+
+fn validate_proxy(proxy: &str) -> Result<(), String> {
+    let (scheme, rest) = proxy
+        .trim()
+        .split_once("://")
+        .ok_or_else(|| "Proxy must look like scheme://host:port".to_string())?;
+    if !PROXY_SCHEMES.contains(&scheme.to_lowercase().as_str()) {
+        return Err(format!(
+            "Unsupported proxy scheme '{}'. Use one of: {}",
+            scheme,
+            PROXY_SCHEMES.join(", ")
+        ));
+    }
+
+    // Credentials may come before the host as user:pass@
+    let authority = rest.split('/').next().unwrap_or("");
+    let host_port = authority.rsplit('@').next().unwrap_or("");
+    let (host, port) = match host_port.strip_prefix('[') {
+        // Bracketed IPv6 literal, e.g. [::1]:1080
+        Some(bracketed) => {
+            let (host, rest) = bracketed
+                .split_once(']')
+                .ok_or_else(|| "Unclosed '[' in proxy host".to_string())?;
+            (host, rest.strip_prefix(':'))
+        }
+        None => match host_port.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (host_port, None),
+        },
+    };
+    if host.is_empty() {
+        return Err("Proxy is missing a host".to_string());
+    }
+    if let Some(port) = port {
+        port.parse::<u16>()
+            .map_err(|_| format!("Invalid proxy port '{}'", port))?;
+    }
+    Ok(())
+}
+
+// Tauri commands for network settings
+#[tauri::command]
+pub async fn get_network_settings(state: State<'_, AppState>) -> Result<NetworkSettings, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    Ok(NetworkSettings::load(&db))
+}
+
+#[tauri::command]
+pub async fn set_network_settings(state: State<'_, AppState>, settings: NetworkSettings) -> Result<(), String> {
+    settings.validate()?;
+    let serialized = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.save_setting(NETWORK_KEY, &serialized).map_err(|e| e.to_string())
+}
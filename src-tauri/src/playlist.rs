@@ -0,0 +1,211 @@
+use crate::commands::AppState;
+use crate::credentials::{redact, TempNetrc};
+use crate::downloader::{DownloadRequest, Downloader};
+use crate::error::DownloadError;
+use crate::naming::sanitize_filename;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+use uuid::Uuid;
+
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 500;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlaylistEntry {
+    pub id: String,
+    pub title: String,
+    pub url: String,
+    pub duration: Option<i64>,
+    pub thumbnail: Option<String>,
+    /// 1-based position in the playlist
+    pub index: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlaylistInfo {
+    pub id: Option<String>,
+    pub title: String,
+    pub uploader: Option<String>,
+    pub platform: String,
+    /// Total number of entries, when the site reports it
+    pub entry_count: Option<i64>,
+    pub entries: Vec<PlaylistEntry>,
+    /// 0-based page number this response covers
+    pub page: usize,
+    pub page_size: usize,
+    pub has_more: bool,
+}
+
+impl Downloader {
+    /// Lists one page of a playlist or channel using flat extraction, so entries are not resolved
+    pub async fn get_playlist_info(
+        &self,
+        url: &str,
+        page: usize,
+        page_size: usize,
+    ) -> Result<PlaylistInfo, DownloadError> {
+        let start = page * page_size + 1;
+        let end = start + page_size - 1;
+
+        let netrc = self.temp_netrc()?;
+        let output = self
+            .yt_dlp_command(None)?
+            .args(self.cookie_args(url))
+            .args(netrc.iter().flat_map(TempNetrc::args))
+            .args([
+                "-J",
+                "--flat-playlist",
+                "--no-warnings",
+                "--playlist-items",
+                &format!("{}:{}", start, end),
+                url,
+            ])
+            .output()
+            .await
+            .map_err(|e| DownloadError::from_spawn("yt-dlp", e))?;
+
+        if !output.status.success() {
+            let stderr = redact(netrc.as_ref(), &String::from_utf8_lossy(&output.stderr));
+            return Err(DownloadError::from_stderr(&stderr));
+        }
+
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout)
+            .map_err(|e| DownloadError::Parse { message: e.to_string() })?;
+
+        let entries: Vec<PlaylistEntry> = json["entries"]
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .enumerate()
+                    .filter_map(|(offset, entry)| {
+                        let url = entry["url"]
+                            .as_str()
+                            .or(entry["webpage_url"].as_str())?
+                            .to_string();
+                        let id = entry["id"].as_str().unwrap_or(&url).to_string();
+
+                        Some(PlaylistEntry {
+                            title: entry["title"].as_str().unwrap_or(&id).to_string(),
+                            id,
+                            url,
+                            duration: entry["duration"].as_i64()
+                                .or_else(|| entry["duration"].as_f64().map(|f| f as i64)),
+                            thumbnail: entry["thumbnail"].as_str()
+                                .or_else(|| {
+                                    entry["thumbnails"].as_array()
+                                        .and_then(|thumbs| thumbs.last())
+                                        .and_then(|thumb| thumb["url"].as_str())
+                                })
+                                .map(|s| s.to_string()),
+                            index: entry["playlist_index"].as_u64()
+                                .map(|i| i as usize)
+                                .unwrap_or(start + offset),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let entry_count = json["playlist_count"].as_i64();
+        let has_more = match entry_count {
+            Some(count) => (end as i64) < count,
+            None => entries.len() == page_size,
+        };
+
+        Ok(PlaylistInfo {
+            id: json["id"].as_str().map(|s| s.to_string()),
+            title: json["title"].as_str().unwrap_or("Untitled playlist").to_string(),
+            uploader: json["uploader"].as_str()
+                .or(json["channel"].as_str())
+                .map(|s| s.to_string()),
+            platform: json["extractor"].as_str()
+                .or(json["extractor_key"].as_str())
+                .unwrap_or("unknown").to_string(),
+            entry_count,
+            entries,
+            page,
+            page_size,
+            has_more,
+        })
+    }
+}
+
+// Tauri commands for playlists
+#[tauri::command]
+pub async fn get_playlist_info(
+    app_handle: AppHandle,
+    url: String,
+    page: Option<usize>,
+    page_size: Option<usize>,
+) -> Result<PlaylistInfo, DownloadError> {
+    let page_size = page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let downloader = Downloader::shared(&app_handle);
+    downloader.get_playlist_info(&url, page.unwrap_or(0), page_size).await
+}
+
+/// A selected entry that was not queued
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlaylistEntryFailure {
+    pub index: usize,
+    pub url: String,
+    pub error: DownloadError,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlaylistEnqueueReport {
+    /// Ids of the queued downloads, in entry order
+    pub queued: Vec<String>,
+    /// Entries left out, e.g. because they were already downloaded
+    pub failed: Vec<PlaylistEntryFailure>,
+}
+
+/// Queues the selected entries as individual downloads inside a folder named after the playlist.
+///
+/// `template` carries the download options; its `id`, `url` and title fields are
+/// replaced for every entry. The options are checked before anything is queued; an
+/// entry that still can't be queued is reported without stopping the others.
+#[tauri::command]
+pub async fn enqueue_playlist_entries(
+    app_handle: AppHandle,
+    playlist_title: String,
+    entries: Vec<PlaylistEntry>,
+    template: DownloadRequest,
+) -> Result<PlaylistEnqueueReport, DownloadError> {
+    {
+        let state = app_handle.state::<AppState>();
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        crate::queue::validate(&db, &mut template.clone())?;
+    }
+
+    let folder = Path::new(&template.output_path).join(sanitize_filename(&playlist_title));
+    let output_path = folder.to_string_lossy().to_string();
+
+    let mut report = PlaylistEnqueueReport {
+        queued: Vec::with_capacity(entries.len()),
+        failed: Vec::new(),
+    };
+    for entry in entries {
+        let (index, url) = (entry.index, entry.url.clone());
+        let request = DownloadRequest {
+            id: Uuid::new_v4().to_string(),
+            url: entry.url,
+            output_path: output_path.clone(),
+            thumbnail: entry.thumbnail,
+            playlist_title: Some(playlist_title.clone()),
+            playlist_index: Some(entry.index),
+            title: Some(entry.title),
+            ..template.clone()
+        };
+        let id = request.id.clone();
+        match crate::queue::enqueue(&app_handle, request) {
+            Ok(()) => report.queued.push(id),
+            Err(error) => {
+                println!("[Playlist] Entry {} was not queued: {}", index, error);
+                report.failed.push(PlaylistEntryFailure { index, url, error });
+            }
+        }
+    }
+
+    Ok(report)
+}
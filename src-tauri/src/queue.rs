@@ -0,0 +1,561 @@
+use crate::commands::AppState;
+use crate::database::{Database, DbResult, Download, QueueEntry};
+use crate::downloader::{DownloadOutcome, DownloadProgress, DownloadRequest, Downloader, MediaSummary};
+use crate::error::DownloadError;
+use crate::retry::RetryPolicy;
+use crate::scheduler::{platform_key, Scheduler};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+// Queue states persisted in the `download_queue` table
+pub const STATE_QUEUED: &str = "queued";
+pub const STATE_RUNNING: &str = "running";
+pub const STATE_COMPLETED: &str = "completed";
+pub const STATE_FAILED: &str = "failed";
+pub const STATE_CANCELLED: &str = "cancelled";
+pub const STATE_PAUSED: &str = "paused";
+pub const STATE_RETRYING: &str = "retrying";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QueueItem {
+    pub id: String,
+    pub request: DownloadRequest,
+    pub state: String,
+    pub attempts: i64,
+    pub last_error: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl TryFrom<QueueEntry> for QueueItem {
+    type Error = String;
+
+    fn try_from(entry: QueueEntry) -> Result<Self, Self::Error> {
+        let request = serde_json::from_str(&entry.request)
+            .map_err(|e| format!("Corrupt queue entry {}: {}", entry.id, e))?;
+
+        Ok(QueueItem {
+            id: entry.id,
+            request,
+            state: entry.state,
+            attempts: entry.attempts,
+            last_error: entry.last_error,
+            created_at: entry.created_at,
+            updated_at: entry.updated_at,
+        })
+    }
+}
+
+/// Moves a job to a new state, mirroring it onto the `downloads` history row
+fn set_state(app: &AppHandle, id: &str, state: &str, last_error: Option<&str>) {
+    let app_state = app.state::<AppState>();
+    let db = match app_state.db.lock() {
+        Ok(db) => db,
+        Err(e) => {
+            println!("[Queue] Failed to lock database: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = db.update_job_state(id, state, last_error) {
+        println!("[Queue] Failed to update job {}: {}", id, e);
+    }
+    if let Err(e) = db.update_download_status(id, history_status(state)) {
+        println!("[Queue] Failed to update history for {}: {}", id, e);
+    }
+}
+
+/// The status shown in download history for a queue state
+fn history_status(state: &str) -> &str {
+    match state {
+        STATE_RUNNING => "downloading",
+        other => other,
+    }
+}
+
+/// Creates the history row for a request unless the frontend already added one
+fn record_history(db: &Database, request: &DownloadRequest, canonical_id: &str) -> DbResult<()> {
+    let format = if request.audio_only {
+        "audio".to_string()
+    } else {
+        request
+            .format
+            .clone()
+            .or_else(|| request.quality.clone())
+            .unwrap_or_else(|| "best".to_string())
+    };
+
+    db.insert_download_if_missing(&Download {
+        id: request.id.clone(),
+        title: request.title.clone().unwrap_or_else(|| request.url.clone()),
+        url: request.url.clone(),
+        format,
+        path: request.output_path.clone(),
+        timestamp: Utc::now().timestamp_millis(),
+        status: STATE_QUEUED.to_string(),
+        size_bytes: None,
+        platform: request.platform.clone().or_else(|| Some(platform_key(&request.url))),
+        thumbnail: request.thumbnail.clone(),
+        parent_id: None,
+        clip: crate::clips::label(&request.clip_ranges),
+        canonical_id: Some(canonical_id.to_string()),
+    })?;
+    // A row added by the frontend may predate canonicalization
+    db.update_download_canonical_id(&request.id, canonical_id)
+}
+
+/// Fills in the title, platform and thumbnail yt-dlp reported for a running download,
+/// and its extractor id when the URL alone didn't identify the media
+pub fn record_metadata(app: &AppHandle, id: &str, url: &str, metadata: &MediaSummary) {
+    let state = app.state::<AppState>();
+    if let Ok(db) = state.db.lock() {
+        let _ = db.update_download_metadata(
+            id,
+            metadata.title.as_deref(),
+            metadata.extractor.as_deref(),
+            metadata.thumbnail.as_deref(),
+        );
+        if crate::canonical::known_id(url).is_none() {
+            if let Some(canonical_id) = crate::canonical::from_info(metadata.extractor_key.as_deref(), metadata.id.as_deref()) {
+                let _ = db.update_download_canonical_id(id, &canonical_id);
+            }
+        }
+    };
+}
+
+/// Records a pause in both the queue and the download history
+fn mark_paused(app: &AppHandle, id: &str, progress: f64) {
+    set_state(app, id, STATE_PAUSED, None);
+    let _ = app.emit("download-progress", DownloadProgress {
+        id: id.to_string(),
+        progress,
+        speed: String::new(),
+        eta: String::new(),
+        status: STATE_PAUSED.to_string(),
+        downloaded_bytes: None,
+        total_bytes: None,
+        filename: None,
+        error: None,
+    });
+}
+
+/// Checks the options of a request, filling in the saved audio settings.
+///
+/// Also used on the shared options of a batch or playlist before any entry is queued.
+pub fn validate(db: &Database, request: &mut DownloadRequest) -> Result<(), DownloadError> {
+    crate::audio::resolve(db, request)?;
+    crate::subtitles::validate(request)?;
+    crate::clips::validate(request)?;
+    if let Some(template) = &request.filename_template {
+        crate::naming::validate_template(template).map_err(DownloadError::invalid)?;
+    }
+    if let Some(network) = &request.network {
+        network.validate().map_err(DownloadError::invalid)?;
+    }
+    Ok(())
+}
+
+/// Persists a request in the queue and hands it to the scheduler.
+///
+/// Media that was already downloaded in full is skipped or reported according to the
+/// duplicate policy; clips are never treated as duplicates.
+pub fn enqueue(app: &AppHandle, mut request: DownloadRequest) -> Result<(), DownloadError> {
+    let (canonical_id, duplicate) = {
+        let state = app.state::<AppState>();
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        // Validate before anything is persisted so a bad request never reaches the queue
+        validate(&db, &mut request)?;
+        let canonical_id = crate::canonical::resolve(&db, &request.url);
+        let duplicate = if request.clip_ranges.is_empty() {
+            crate::canonical::check_duplicate(&db, &request.id, &request.url, &canonical_id)?
+        } else {
+            None
+        };
+
+        let serialized = serde_json::to_string(&request).map_err(|e| e.to_string())?;
+        if !db.enqueue_job(&request.id, &serialized).map_err(|e| e.to_string())? {
+            return Err(already_queued(&request.id));
+        }
+        record_history(&db, &request, &canonical_id).map_err(|e| e.to_string())?;
+        db.update_download_status(&request.id, STATE_QUEUED).map_err(|e| e.to_string())?;
+        (canonical_id, duplicate)
+    };
+
+    if let Some(existing) = duplicate {
+        crate::canonical::emit_warning(app, &request.id, &request.url, &canonical_id, existing);
+    }
+
+    let _ = app.emit("download-progress", DownloadProgress {
+        id: request.id.clone(),
+        progress: 0.0,
+        speed: String::new(),
+        eta: String::new(),
+        status: STATE_QUEUED.to_string(),
+        downloaded_bytes: None,
+        total_bytes: None,
+        filename: None,
+        error: None,
+    });
+
+    app.state::<Scheduler>().submit(app, request);
+    Ok(())
+}
+
+fn already_queued(id: &str) -> DownloadError {
+    DownloadError::invalid(format!("Download {} is already in the queue", id))
+}
+
+/// Re-enqueues every job that was queued or running when the app last exited
+pub fn recover(app: &AppHandle) -> Result<usize, String> {
+    let entries = {
+        let state = app.state::<AppState>();
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        db.get_unfinished_jobs().map_err(|e| e.to_string())?
+    };
+
+    let mut recovered = 0;
+    for entry in entries {
+        let id = entry.id.clone();
+        match QueueItem::try_from(entry) {
+            Ok(item) => {
+                set_state(app, &item.id, STATE_QUEUED, None);
+                app.state::<Scheduler>().submit(app, item.request);
+                recovered += 1;
+            }
+            Err(e) => {
+                println!("[Queue] {}", e);
+                set_state(app, &id, STATE_FAILED, Some(&e));
+            }
+        }
+    }
+
+    println!("[Queue] Recovered {} unfinished download(s)", recovered);
+    Ok(recovered)
+}
+
+/// Opens the attempt a run belongs to; a run continuing after a pause or relaunch
+/// keeps the attempt that run started
+fn begin_attempt(db: &Database, id: &str) -> DbResult<i64> {
+    if let Some(attempt_id) = db.get_open_attempt(id)? {
+        return Ok(attempt_id);
+    }
+    let failures = db.get_job(id)?.map(|job| job.attempts).unwrap_or(0);
+    db.start_attempt(id, failures + 1)
+}
+
+/// Closes the attempt unless the run is only interrupted, and returns how many attempts
+/// have failed; only failures count towards the retry limit
+fn end_attempt(db: &Database, id: &str, attempt_id: Option<i64>, outcome: &DownloadOutcome) -> DbResult<i64> {
+    if matches!(outcome, DownloadOutcome::Paused | DownloadOutcome::Relaunched) {
+        return Ok(db.get_job(id)?.map(|job| job.attempts).unwrap_or(0));
+    }
+    if let Some(attempt_id) = attempt_id {
+        let reason = match outcome {
+            DownloadOutcome::Failed(error) => Some(error.to_string()),
+            _ => None,
+        };
+        db.finish_attempt(attempt_id, outcome.status(), reason.as_deref())?;
+    }
+    match outcome {
+        DownloadOutcome::Failed(_) => db.increment_job_attempts(id),
+        _ => Ok(db.get_job(id)?.map(|job| job.attempts).unwrap_or(0)),
+    }
+}
+
+/// Runs a job the scheduler has given a slot, releasing the slot when it ends
+pub fn run_job(app: AppHandle, request: DownloadRequest, rate_limit: Option<u64>) {
+    tauri::async_runtime::spawn(async move {
+        let id = request.id.clone();
+        set_state(&app, &id, STATE_RUNNING, None);
+
+        let attempt_id = {
+            let state = app.state::<AppState>();
+            let db = state.db.lock().ok();
+            db.and_then(|db| begin_attempt(&db, &id).ok())
+        };
+
+        let downloader = Downloader::shared(&app);
+        let outcome = downloader
+            .run_download(request.clone(), app.clone(), rate_limit)
+            .await
+            .unwrap_or_else(DownloadOutcome::Failed);
+
+        let attempts = {
+            let state = app.state::<AppState>();
+            let db = state.db.lock().ok();
+            db.and_then(|db| end_attempt(&db, &id, attempt_id, &outcome).ok())
+                .unwrap_or(1)
+        };
+
+        match outcome {
+            DownloadOutcome::Completed(done) => {
+                set_state(&app, &id, STATE_COMPLETED, None);
+                let state = app.state::<AppState>();
+                if let Ok(db) = state.db.lock() {
+                    let _ = db.update_download_result(&id, done.filepath.as_deref(), done.size_bytes);
+                    if request.subtitles.is_some() {
+                        let _ = crate::subtitles::record(&db, &id, &done.subtitles);
+                    }
+                    if !done.chapters.is_empty() {
+                        let _ = crate::chapters::record(&db, &request, &done.chapters);
+                    }
+                    if !done.clips.is_empty() {
+                        let _ = crate::clips::record(&db, &request, &done.clips);
+                    }
+                };
+            }
+            DownloadOutcome::Cancelled => set_state(&app, &id, STATE_CANCELLED, None),
+            DownloadOutcome::Paused => set_state(&app, &id, STATE_PAUSED, None),
+            DownloadOutcome::Relaunched => {
+                // Goes straight back to the front of the line instead of releasing its slot
+                set_state(&app, &id, STATE_QUEUED, None);
+                let request = persisted_request(&app, &id).unwrap_or(request);
+                app.state::<Scheduler>().requeue(&app, request);
+                return;
+            }
+            DownloadOutcome::Failed(error) => {
+                let policy = {
+                    let state = app.state::<AppState>();
+                    let policy = state.db.lock().map(|db| RetryPolicy::load(&db));
+                    policy.unwrap_or_default()
+                };
+
+                let reason = error.to_string();
+                match policy.should_retry(attempts as u32, &error) {
+                    Some(class) => {
+                        let delay = policy.delay_after(attempts as u32);
+                        println!(
+                            "[Queue] Attempt {} of {} failed ({:?}), retrying in {}s",
+                            attempts, id, class, delay.as_secs()
+                        );
+                        // Pick up the output name fixed during this run
+                        let request = persisted_request(&app, &id).unwrap_or(request);
+                        schedule_retry(app.clone(), request, delay, error);
+                    }
+                    None => {
+                        set_state(&app, &id, STATE_FAILED, Some(&reason));
+                        let _ = app.emit("download-progress", DownloadProgress {
+                            id: id.clone(),
+                            progress: 0.0,
+                            speed: String::new(),
+                            eta: String::new(),
+                            status: STATE_FAILED.to_string(),
+                            downloaded_bytes: None,
+                            total_bytes: None,
+                            filename: None,
+                            error: Some(error),
+                        });
+                    }
+                }
+            }
+        }
+
+        app.state::<Scheduler>().finish(&app, &id);
+    });
+}
+
+/// Waits out the backoff delay without holding a scheduler slot, then re-submits
+fn schedule_retry(app: AppHandle, request: DownloadRequest, delay: Duration, error: DownloadError) {
+    let id = request.id.clone();
+    set_state(&app, &id, STATE_RETRYING, Some(&error.to_string()));
+    let _ = app.emit("download-progress", DownloadProgress {
+        id: id.clone(),
+        progress: 0.0,
+        speed: String::new(),
+        eta: format!("{}s", delay.as_secs()),
+        status: STATE_RETRYING.to_string(),
+        downloaded_bytes: None,
+        total_bytes: None,
+        filename: None,
+        error: Some(error),
+    });
+
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(delay).await;
+
+        // The job may have been cancelled or paused while we were waiting
+        if job_state(&app, &id).as_deref() != Some(STATE_RETRYING) {
+            return;
+        }
+        set_state(&app, &id, STATE_QUEUED, None);
+        app.state::<Scheduler>().submit(&app, request);
+    });
+}
+
+fn persisted_request(app: &AppHandle, id: &str) -> Option<DownloadRequest> {
+    let state = app.state::<AppState>();
+    let db = state.db.lock().ok()?;
+    let entry = db.get_job(id).ok().flatten()?;
+    QueueItem::try_from(entry).ok().map(|item| item.request)
+}
+
+fn job_state(app: &AppHandle, id: &str) -> Option<String> {
+    let state = app.state::<AppState>();
+    let db = state.db.lock().ok()?;
+    db.get_job(id).ok().flatten().map(|entry| entry.state)
+}
+
+/// Cancels a job that is waiting for a scheduler slot, a retry, or is paused
+pub fn cancel_waiting(app: &AppHandle, id: &str) -> bool {
+    let was_pending = app.state::<Scheduler>().remove_pending(id);
+    let waiting = matches!(job_state(app, id).as_deref(), Some(STATE_PAUSED) | Some(STATE_RETRYING));
+    if !was_pending && !waiting {
+        return false;
+    }
+
+    set_state(app, id, STATE_CANCELLED, None);
+    // A paused or relaunched run leaves its attempt open for the run that continues it
+    {
+        let state = app.state::<AppState>();
+        if let Ok(db) = state.db.lock() {
+            if let Ok(Some(attempt_id)) = db.get_open_attempt(id) {
+                let _ = db.finish_attempt(attempt_id, STATE_CANCELLED, None);
+            }
+        };
+    }
+    let _ = app.emit("download-progress", DownloadProgress {
+        id: id.to_string(),
+        progress: 0.0,
+        speed: String::new(),
+        eta: String::new(),
+        status: STATE_CANCELLED.to_string(),
+        downloaded_bytes: None,
+        total_bytes: None,
+        filename: None,
+        error: None,
+    });
+    true
+}
+
+/// Pauses a job that has not been given a scheduler slot yet or is waiting to retry
+pub fn pause_pending(app: &AppHandle, id: &str) -> bool {
+    let was_pending = app.state::<Scheduler>().remove_pending(id);
+    if !was_pending && job_state(app, id).as_deref() != Some(STATE_RETRYING) {
+        return false;
+    }
+
+    mark_paused(app, id, 0.0);
+    true
+}
+
+/// Puts a paused job back in line; yt-dlp continues from its .part files
+pub fn resume(app: &AppHandle, id: &str) -> Result<(), String> {
+    let entry = {
+        let state = app.state::<AppState>();
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        db.get_job(id).map_err(|e| e.to_string())?
+    };
+
+    let item = match entry {
+        Some(entry) if entry.state == STATE_PAUSED => QueueItem::try_from(entry)?,
+        Some(_) => return Err("Download is not paused".to_string()),
+        None => return Err("Download not found".to_string()),
+    };
+
+    set_state(app, id, STATE_QUEUED, None);
+    let _ = app.emit("download-progress", DownloadProgress {
+        id: id.to_string(),
+        progress: 0.0,
+        speed: String::new(),
+        eta: String::new(),
+        status: STATE_QUEUED.to_string(),
+        downloaded_bytes: None,
+        total_bytes: None,
+        filename: None,
+        error: None,
+    });
+
+    app.state::<Scheduler>().submit(app, item.request);
+    Ok(())
+}
+
+// Tauri commands for the download queue
+#[tauri::command]
+pub async fn get_download_queue(state: State<'_, AppState>) -> Result<Vec<QueueItem>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let entries = db.get_queue().map_err(|e| e.to_string())?;
+    Ok(entries
+        .into_iter()
+        .filter_map(|entry| QueueItem::try_from(entry).ok())
+        .collect())
+}
+
+#[tauri::command]
+pub async fn remove_from_queue(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    match db.get_job(&id).map_err(|e| e.to_string())? {
+        Some(entry) if [STATE_RUNNING, STATE_QUEUED, STATE_RETRYING].contains(&entry.state.as_str()) => {
+            Err("Cannot remove an active download; cancel it first".to_string())
+        }
+        Some(_) => db.remove_job(&id).map_err(|e| e.to_string()),
+        None => Err("Queue entry not found".to_string()),
+    }
+}
+
+#[tauri::command]
+pub async fn clear_finished_queue(state: State<'_, AppState>) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.clear_finished_jobs().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database() -> (Database, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("omni-queue-{}", uuid::Uuid::new_v4()));
+        let db = Database::new(dir.clone()).unwrap();
+        db.enqueue_job("job", "{}").unwrap();
+        (db, dir)
+    }
+
+    #[test]
+    fn relaunch_and_pause_do_not_count_as_attempts() {
+        let (db, dir) = database();
+
+        let first = begin_attempt(&db, "job").unwrap();
+        assert_eq!(end_attempt(&db, "job", Some(first), &DownloadOutcome::Relaunched).unwrap(), 0);
+        let second = begin_attempt(&db, "job").unwrap();
+        assert_eq!(second, first);
+        assert_eq!(end_attempt(&db, "job", Some(second), &DownloadOutcome::Paused).unwrap(), 0);
+        assert_eq!(db.get_job("job").unwrap().unwrap().attempts, 0);
+
+        let third = begin_attempt(&db, "job").unwrap();
+        assert_eq!(third, first);
+        let failure = DownloadOutcome::Failed(DownloadError::Network { message: "timed out".to_string() });
+        assert_eq!(end_attempt(&db, "job", Some(third), &failure).unwrap(), 1);
+        assert_eq!(db.get_job("job").unwrap().unwrap().attempts, 1);
+
+        let retry = begin_attempt(&db, "job").unwrap();
+        assert_ne!(retry, first);
+        let attempts = db.get_download_attempts("job").unwrap();
+        assert_eq!(attempts.len(), 2);
+        assert_eq!(attempts[1].attempt, 2);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn enqueueing_an_unfinished_job_again_changes_nothing() {
+        let (db, dir) = database();
+        let attempt = begin_attempt(&db, "job").unwrap();
+        let failure = DownloadOutcome::Failed(DownloadError::Network { message: "timed out".to_string() });
+        end_attempt(&db, "job", Some(attempt), &failure).unwrap();
+
+        for state in [STATE_RUNNING, STATE_PAUSED, STATE_RETRYING, STATE_QUEUED] {
+            db.update_job_state("job", state, None).unwrap();
+            assert!(!db.enqueue_job("job", "{\"new\":true}").unwrap(), "{}", state);
+            let job = db.get_job("job").unwrap().unwrap();
+            assert_eq!(job.state, state);
+            assert_eq!(job.attempts, 1);
+            assert_eq!(job.request, "{}");
+        }
+
+        db.update_job_state("job", STATE_FAILED, Some("timed out")).unwrap();
+        assert!(db.enqueue_job("job", "{}").unwrap());
+        let job = db.get_job("job").unwrap().unwrap();
+        assert_eq!(job.state, STATE_QUEUED);
+        assert_eq!(job.attempts, 0);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
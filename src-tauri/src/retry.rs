@@ -0,0 +1,109 @@
+use crate::commands::AppState;
+use crate::database::{Database, DownloadAttempt};
+use crate::error::DownloadError;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::State;
+
+// Settings key the retry policy is persisted under
+const RETRY_POLICY_KEY: &str = "retry_policy";
+
+/// Failure classes that are worth retrying automatically
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryableClass {
+    /// HTTP 429 / "Too Many Requests"
+    RateLimited,
+    /// HTTP 5xx responses
+    ServerError,
+    /// Connection resets, timeouts and other transport failures
+    Network,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RetryPolicy {
+    /// Total number of runs, including the first one
+    pub max_attempts: u32,
+    pub initial_delay_secs: u64,
+    pub max_delay_secs: u64,
+    pub multiplier: f64,
+    pub retry_on: Vec<RetryableClass>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_delay_secs: 5,
+            max_delay_secs: 300,
+            multiplier: 2.0,
+            retry_on: vec![
+                RetryableClass::RateLimited,
+                RetryableClass::ServerError,
+                RetryableClass::Network,
+            ],
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn load(db: &Database) -> Self {
+        db.get_setting(RETRY_POLICY_KEY)
+            .ok()
+            .flatten()
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_attempts == 0 {
+            return Err("max_attempts must be at least 1".to_string());
+        }
+        if self.multiplier < 1.0 {
+            return Err("multiplier must be at least 1.0".to_string());
+        }
+        if self.max_delay_secs < self.initial_delay_secs {
+            return Err("max_delay_secs must not be smaller than initial_delay_secs".to_string());
+        }
+        Ok(())
+    }
+
+    /// Delay before the next run, given how many runs have already happened
+    pub fn delay_after(&self, attempts: u32) -> Duration {
+        let exponent = attempts.saturating_sub(1).min(32) as i32;
+        let secs = self.initial_delay_secs as f64 * self.multiplier.powi(exponent);
+        Duration::from_secs(secs.min(self.max_delay_secs as f64) as u64)
+    }
+
+    /// Returns the class that makes this failure retryable, if the policy allows another run
+    pub fn should_retry(&self, attempts: u32, error: &DownloadError) -> Option<RetryableClass> {
+        if attempts >= self.max_attempts {
+            return None;
+        }
+        error.retryable_class().filter(|class| self.retry_on.contains(class))
+    }
+}
+
+// Tauri commands for the retry policy
+#[tauri::command]
+pub async fn get_retry_policy(state: State<'_, AppState>) -> Result<RetryPolicy, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    Ok(RetryPolicy::load(&db))
+}
+
+#[tauri::command]
+pub async fn set_retry_policy(state: State<'_, AppState>, policy: RetryPolicy) -> Result<(), String> {
+    policy.validate()?;
+    let serialized = serde_json::to_string(&policy).map_err(|e| e.to_string())?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.save_setting(RETRY_POLICY_KEY, &serialized).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_download_attempts(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<Vec<DownloadAttempt>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_download_attempts(&id).map_err(|e| e.to_string())
+}
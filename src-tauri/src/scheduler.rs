@@ -0,0 +1,352 @@
+use crate::bandwidth::BandwidthSettings;
+use crate::commands::AppState;
+use crate::database::Database;
+use crate::downloader::DownloadRequest;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use tauri::{AppHandle, State};
+
+pub const DEFAULT_MAX_CONCURRENT: usize = 3;
+
+// Settings keys the scheduler limits are persisted under
+const MAX_CONCURRENT_KEY: &str = "max_concurrent_downloads";
+const PLATFORM_LIMITS_KEY: &str = "platform_concurrency_limits";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScheduledJob {
+    pub id: String,
+    pub platform: String,
+    /// Bytes per second the job was launched with; always `None` while pending
+    pub rate_limit: Option<u64>,
+}
+
+/// A launched job and the bandwidth limit its yt-dlp process was started with
+struct RunningJob {
+    platform: String,
+    rate_limit: Option<u64>,
+    // Jobs with their own limit ignore the global schedule
+    overridden: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SchedulerStatus {
+    pub max_concurrent: usize,
+    pub platform_limits: HashMap<String, usize>,
+    pub running: Vec<ScheduledJob>,
+    pub pending: Vec<ScheduledJob>,
+}
+
+struct SchedulerState {
+    pending: VecDeque<DownloadRequest>,
+    running: HashMap<String, RunningJob>,
+    max_concurrent: usize,
+    platform_limits: HashMap<String, usize>,
+    bandwidth: BandwidthSettings,
+}
+
+impl SchedulerState {
+    fn running_on(&self, platform: &str) -> usize {
+        self.running.values().filter(|job| job.platform == platform).count()
+    }
+
+    /// Takes the oldest pending request whose platform still has a free slot
+    fn next_runnable(&mut self) -> Option<DownloadRequest> {
+        if self.running.len() >= self.max_concurrent {
+            return None;
+        }
+
+        let index = self.pending.iter().position(|request| {
+            let platform = platform_key(&request.url);
+            match self.platform_limits.get(&platform) {
+                Some(limit) => self.running_on(&platform) < *limit,
+                None => true,
+            }
+        })?;
+
+        self.pending.remove(index)
+    }
+}
+
+/// Holds pending downloads and launches at most `max_concurrent` of them at a time
+pub struct Scheduler {
+    state: Mutex<SchedulerState>,
+}
+
+impl Scheduler {
+    pub fn new(
+        max_concurrent: usize,
+        platform_limits: HashMap<String, usize>,
+        bandwidth: BandwidthSettings,
+    ) -> Self {
+        Self {
+            state: Mutex::new(SchedulerState {
+                pending: VecDeque::new(),
+                running: HashMap::new(),
+                max_concurrent: max_concurrent.max(1),
+                platform_limits,
+                bandwidth,
+            }),
+        }
+    }
+
+    /// Builds a scheduler from the limits saved in the settings table
+    pub fn from_settings(db: &Database) -> Self {
+        let max_concurrent = db
+            .get_setting(MAX_CONCURRENT_KEY)
+            .ok()
+            .flatten()
+            .and_then(|value| value.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_MAX_CONCURRENT);
+
+        let platform_limits = db
+            .get_setting(PLATFORM_LIMITS_KEY)
+            .ok()
+            .flatten()
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default();
+
+        Self::new(max_concurrent, platform_limits, BandwidthSettings::load(db))
+    }
+
+    /// Adds a request to the back of the pending list and starts whatever fits
+    pub fn submit(&self, app: &AppHandle, request: DownloadRequest) {
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.running.contains_key(&request.id)
+                || state.pending.iter().any(|pending| pending.id == request.id)
+            {
+                return;
+            }
+            state.pending.push_back(request);
+        }
+        self.pump(app);
+    }
+
+    /// Drops a request that has not started yet, returning whether it was pending
+    pub fn remove_pending(&self, id: &str) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let before = state.pending.len();
+        state.pending.retain(|request| request.id != id);
+        state.pending.len() != before
+    }
+
+    /// Releases the slot held by a finished download and starts the next ones
+    pub fn finish(&self, app: &AppHandle, id: &str) {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.running.remove(id);
+        }
+        self.pump(app);
+    }
+
+    /// Puts a running job back at the front of the line, e.g. to restart it with a new limit
+    pub fn requeue(&self, app: &AppHandle, request: DownloadRequest) {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.running.remove(&request.id);
+            state.pending.push_front(request);
+        }
+        self.pump(app);
+    }
+
+    /// Launches pending downloads until the global or per-platform limits are reached,
+    /// each with the bandwidth limit in force right now
+    pub fn pump(&self, app: &AppHandle) {
+        loop {
+            let (request, rate_limit) = {
+                let mut state = self.state.lock().unwrap();
+                let Some(request) = state.next_runnable() else {
+                    break;
+                };
+                let overridden = request.rate_limit.is_some();
+                let rate_limit = request.rate_limit.or_else(|| state.bandwidth.current_limit());
+                state.running.insert(request.id.clone(), RunningJob {
+                    platform: platform_key(&request.url),
+                    rate_limit,
+                    overridden,
+                });
+                (request, rate_limit)
+            };
+            crate::queue::run_job(app.clone(), request, rate_limit);
+        }
+    }
+
+    /// Relaunches running downloads whose limit no longer matches the schedule;
+    /// yt-dlp can't change its rate on the fly, but continues from its .part files
+    pub fn apply_bandwidth(&self, app: &AppHandle) {
+        let stale: Vec<String> = {
+            let state = self.state.lock().unwrap();
+            let limit = state.bandwidth.current_limit();
+            state
+                .running
+                .iter()
+                .filter(|(_, job)| !job.overridden && job.rate_limit != limit)
+                .map(|(id, _)| id.clone())
+                .collect()
+        };
+
+        for id in stale {
+            if crate::downloader::relaunch_download(&id) {
+                println!("[Scheduler] Relaunching {} with the new bandwidth limit", id);
+            }
+        }
+        self.pump(app);
+    }
+
+    /// Picks up limits written to the settings table directly, e.g. by a database import;
+    /// call `apply_bandwidth` afterwards so running and pending downloads follow them
+    pub fn reload_settings(&self, db: &Database) {
+        let loaded = Self::from_settings(db).state.into_inner().unwrap();
+        let mut state = self.state.lock().unwrap();
+        state.max_concurrent = loaded.max_concurrent;
+        state.platform_limits = loaded.platform_limits;
+        state.bandwidth = loaded.bandwidth;
+    }
+
+    pub fn current_bandwidth_limit(&self) -> Option<u64> {
+        self.state.lock().unwrap().bandwidth.current_limit()
+    }
+
+    pub(crate) fn set_bandwidth(&self, bandwidth: BandwidthSettings) {
+        self.state.lock().unwrap().bandwidth = bandwidth;
+    }
+
+    pub fn status(&self) -> SchedulerStatus {
+        let state = self.state.lock().unwrap();
+        SchedulerStatus {
+            max_concurrent: state.max_concurrent,
+            platform_limits: state.platform_limits.clone(),
+            running: state
+                .running
+                .iter()
+                .map(|(id, job)| ScheduledJob {
+                    id: id.clone(),
+                    platform: job.platform.clone(),
+                    rate_limit: job.rate_limit,
+                })
+                .collect(),
+            pending: state
+                .pending
+                .iter()
+                .map(|request| ScheduledJob {
+                    id: request.id.clone(),
+                    platform: platform_key(&request.url),
+                    rate_limit: None,
+                })
+                .collect(),
+        }
+    }
+
+    fn set_max_concurrent(&self, limit: usize) {
+        self.state.lock().unwrap().max_concurrent = limit;
+    }
+
+    fn set_platform_limit(&self, platform: &str, limit: Option<usize>) -> HashMap<String, usize> {
+        let mut state = self.state.lock().unwrap();
+        match limit {
+            Some(limit) => {
+                state.platform_limits.insert(platform.to_string(), limit);
+            }
+            None => {
+                state.platform_limits.remove(platform);
+            }
+        }
+        state.platform_limits.clone()
+    }
+}
+
+/// The lowercased host of a URL, without credentials or port
+pub fn url_host(url: &str) -> String {
+    let without_scheme = url.split("://").nth(1).unwrap_or(url);
+    let authority = without_scheme
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or("");
+    authority
+        .rsplit('@')
+        .next()
+        .unwrap_or("")
+        .split(':')
+        .next()
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+/// Derives a platform key such as "youtube" or "vimeo" from a URL's host
+pub fn platform_key(url: &str) -> String {
+    let host = url_host(url);
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+
+    if host == "youtu.be" {
+        return "youtube".to_string();
+    }
+
+    // Use the label before the public suffix, e.g. "music.youtube.com" -> "youtube"
+    let labels: Vec<&str> = host.split('.').filter(|l| !l.is_empty()).collect();
+    match labels.len() {
+        0 => "unknown".to_string(),
+        1 => labels[0].to_string(),
+        n if n >= 3 && labels[n - 2].len() <= 3 && labels[n - 1].len() == 2 => {
+            // Country-code second-level domains like "bbc.co.uk"
+            labels[n - 3].to_string()
+        }
+        n => labels[n - 2].to_string(),
+    }
+}
+
+// Tauri commands for the scheduler
+#[tauri::command]
+pub async fn get_scheduler_status(scheduler: State<'_, Scheduler>) -> Result<SchedulerStatus, String> {
+    Ok(scheduler.status())
+}
+
+#[tauri::command]
+pub async fn set_max_concurrent_downloads(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    scheduler: State<'_, Scheduler>,
+    limit: usize,
+) -> Result<(), String> {
+    if limit == 0 {
+        return Err("Concurrency limit must be at least 1".to_string());
+    }
+
+    {
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        db.save_setting(MAX_CONCURRENT_KEY, &limit.to_string())
+            .map_err(|e| e.to_string())?;
+    }
+
+    scheduler.set_max_concurrent(limit);
+    scheduler.pump(&app_handle);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_platform_concurrency_limit(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    scheduler: State<'_, Scheduler>,
+    platform: String,
+    limit: Option<usize>,
+) -> Result<(), String> {
+    let platform = platform.trim().to_lowercase();
+    if platform.is_empty() {
+        return Err("Platform must not be empty".to_string());
+    }
+    if limit == Some(0) {
+        return Err("Concurrency limit must be at least 1".to_string());
+    }
+
+    let limits = scheduler.set_platform_limit(&platform, limit);
+    {
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        let serialized = serde_json::to_string(&limits).map_err(|e| e.to_string())?;
+        db.save_setting(PLATFORM_LIMITS_KEY, &serialized)
+            .map_err(|e| e.to_string())?;
+    }
+
+    scheduler.pump(&app_handle);
+    Ok(())
+}
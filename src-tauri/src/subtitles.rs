@@ -0,0 +1,164 @@
+use crate::commands::AppState;
+use crate::database::{Database, DbResult, DownloadSubtitle};
+use crate::downloader::DownloadRequest;
+use crate::error::DownloadError;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+/// A subtitle track a video offers
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubtitleTrack {
+    pub language: String,
+    pub name: Option<String>,
+    /// Generated by the site (e.g. YouTube auto-captions) rather than uploaded
+    pub automatic: bool,
+    /// Formats the site serves this track in, e.g. "vtt", "srv3"
+    pub formats: Vec<String>,
+}
+
+/// Formats subtitles can be converted to with `--convert-subs`
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SubtitleFormat {
+    Srt,
+    Vtt,
+    Ass,
+}
+
+impl SubtitleFormat {
+    pub fn as_arg(&self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Vtt => "vtt",
+            SubtitleFormat::Ass => "ass",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubtitleOptions {
+    /// Language codes or yt-dlp patterns such as "en.*"; "all" selects every track
+    pub languages: Vec<String>,
+    #[serde(default)]
+    pub include_automatic: bool,
+    /// Convert to this format; the site's format is kept when unset
+    pub format: Option<SubtitleFormat>,
+    /// Embed into the video container instead of keeping separate files
+    #[serde(default)]
+    pub embed: bool,
+}
+
+/// A subtitle file written (or embedded) by a finished download
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SubtitleFile {
+    pub language: String,
+    pub format: String,
+    /// Location of the separate file; `None` once embedded
+    pub path: Option<String>,
+    pub embedded: bool,
+}
+
+/// Lists the manual and automatic tracks from `yt-dlp -j` output
+pub fn tracks_from_info(json: &serde_json::Value) -> Vec<SubtitleTrack> {
+    let mut tracks = Vec::new();
+    for (key, automatic) in [("subtitles", false), ("automatic_captions", true)] {
+        let Some(languages) = json[key].as_object() else {
+            continue;
+        };
+        for (language, entries) in languages {
+            // YouTube lists the live chat replay as a subtitle track
+            if language == "live_chat" {
+                continue;
+            }
+            let entries = entries.as_array().map(|a| a.as_slice()).unwrap_or_default();
+            tracks.push(SubtitleTrack {
+                language: language.clone(),
+                name: entries.iter().find_map(|e| e["name"].as_str()).map(|s| s.to_string()),
+                automatic,
+                formats: entries.iter().filter_map(|e| e["ext"].as_str()).map(|s| s.to_string()).collect(),
+            });
+        }
+    }
+    tracks
+}
+
+/// Rejects subtitle options that yt-dlp would not be able to honour
+pub fn validate(request: &DownloadRequest) -> Result<(), DownloadError> {
+    let Some(options) = &request.subtitles else {
+        return Ok(());
+    };
+    if options.languages.is_empty() {
+        return Err(DownloadError::invalid("Select at least one subtitle language"));
+    }
+    if let Some(language) = options
+        .languages
+        .iter()
+        .find(|l| l.trim().is_empty() || l.contains(',') || l.chars().any(char::is_whitespace))
+    {
+        return Err(DownloadError::invalid(format!("Invalid subtitle language: '{}'", language)));
+    }
+    if options.embed && request.audio_only {
+        return Err(DownloadError::invalid("Subtitles cannot be embedded into audio-only downloads"));
+    }
+    Ok(())
+}
+
+/// yt-dlp arguments for the requested subtitles
+pub fn args(options: &SubtitleOptions) -> Vec<String> {
+    let mut args = vec![
+        "--write-subs".to_string(),
+        "--sub-langs".to_string(),
+        options.languages.iter().map(|l| l.trim()).collect::<Vec<_>>().join(","),
+    ];
+    if options.include_automatic {
+        args.push("--write-auto-subs".to_string());
+    }
+    if let Some(format) = options.format {
+        args.extend(["--convert-subs".to_string(), format.as_arg().to_string()]);
+    }
+    if options.embed {
+        args.push("--embed-subs".to_string());
+    }
+    args
+}
+
+/// Reads the `requested_subtitles` map yt-dlp prints after moving the files into place
+pub fn files_from_requested(json: &str, embedded: bool) -> Vec<SubtitleFile> {
+    let Ok(serde_json::Value::Object(requested)) = serde_json::from_str(json) else {
+        return Vec::new();
+    };
+    requested
+        .into_iter()
+        .filter(|(language, _)| language != "live_chat")
+        .map(|(language, entry)| SubtitleFile {
+            language,
+            format: entry["ext"].as_str().unwrap_or("unknown").to_string(),
+            // Embedded subtitle files are deleted once they are in the container
+            path: if embedded {
+                None
+            } else {
+                entry["filepath"].as_str().map(|s| s.to_string())
+            },
+            embedded,
+        })
+        .collect()
+}
+
+/// Replaces the subtitle records of a download with the files from its latest run
+pub fn record(db: &Database, download_id: &str, files: &[SubtitleFile]) -> DbResult<()> {
+    db.clear_download_subtitles(download_id)?;
+    for file in files {
+        db.add_download_subtitle(download_id, &file.language, &file.format, file.path.as_deref(), file.embedded)?;
+    }
+    Ok(())
+}
+
+// Tauri commands for subtitles
+#[tauri::command]
+pub async fn get_download_subtitles(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<Vec<DownloadSubtitle>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_download_subtitles(&id).map_err(|e| e.to_string())
+}
@@ -0,0 +1,281 @@
+use crate::binaries::{version_from_output, Tool};
+use crate::commands::AppState;
+use crate::database::Database;
+use crate::downloader::Downloader;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+
+// Settings key the toolchain settings are persisted under
+pub(crate) const TOOLCHAIN_KEY: &str = "toolchain";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct ToolchainSettings {
+    /// Explicit yt-dlp binary, or a folder containing it; checked before anything else
+    pub yt_dlp_path: Option<String>,
+    /// Explicit ffmpeg binary, or a folder containing ffmpeg and ffprobe
+    pub ffmpeg_path: Option<String>,
+    /// Fall back to tools installed on the system `PATH`, e.g. by a package manager
+    pub search_path: bool,
+}
+
+impl ToolchainSettings {
+    pub fn load(db: &Database) -> Self {
+        db.get_setting(TOOLCHAIN_KEY)
+            .ok()
+            .flatten()
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for path in [&self.yt_dlp_path, &self.ffmpeg_path].into_iter().flatten() {
+            if !Path::new(path.trim()).is_absolute() {
+                return Err(format!("'{}' must be an absolute path", path));
+            }
+        }
+        Ok(())
+    }
+
+    fn custom_path(&self, tool: Tool) -> Option<&str> {
+        match tool {
+            Tool::YtDlp => self.yt_dlp_path.as_deref(),
+            // ffprobe is looked up next to the configured ffmpeg
+            Tool::Ffmpeg | Tool::Ffprobe => self.ffmpeg_path.as_deref(),
+        }
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+    }
+}
+
+/// Where a tool was found, in the order locations are checked
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolSource {
+    Custom,
+    Bundled,
+    AppData,
+    Path,
+}
+
+/// One location checked while resolving a tool
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ToolCandidate {
+    pub source: ToolSource,
+    pub path: String,
+    pub exists: bool,
+    /// Reported by the binary itself; `None` if it was missing or failed to run
+    pub version: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ToolStatus {
+    pub tool: Tool,
+    /// The binary that will be used, if any location worked
+    pub path: Option<String>,
+    pub version: Option<String>,
+    pub source: Option<ToolSource>,
+    pub checked: Vec<ToolCandidate>,
+}
+
+impl ToolStatus {
+    fn selected(&self) -> Option<&ToolCandidate> {
+        self.checked.iter().find(|candidate| candidate.version.is_some())
+    }
+}
+
+/// A hidden synchronous command, so probing never flashes a console window on Windows
+fn probe_command(path: &Path) -> std::process::Command {
+    #[allow(unused_mut)]
+    let mut command = std::process::Command::new(path);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        // CREATE_NO_WINDOW = 0x08000000
+        command.creation_flags(0x08000000);
+    }
+    command
+}
+
+fn probe(path: &Path, tool: Tool) -> Result<String, String> {
+    let output = probe_command(path)
+        .arg(tool.version_arg())
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!("exited with {}", output.status));
+    }
+    Ok(version_from_output(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Every location to try for `tool`, in priority order and without duplicates
+fn candidate_paths(app_handle: &AppHandle, settings: &ToolchainSettings, tool: Tool) -> Vec<(ToolSource, PathBuf)> {
+    let file_name = tool.file_name();
+    let mut paths = Vec::new();
+
+    if let Some(custom) = settings.custom_path(tool) {
+        let custom = PathBuf::from(custom);
+        let path = if custom.is_dir() {
+            custom.join(&file_name)
+        } else if tool == Tool::Ffprobe {
+            custom.with_file_name(&file_name)
+        } else {
+            custom
+        };
+        paths.push((ToolSource::Custom, path));
+    }
+
+    if let Ok(resource_dir) = app_handle.path().resource_dir() {
+        let mut names = Vec::new();
+        // Bundled macOS builds keep the release asset name
+        if tool == Tool::YtDlp && cfg!(target_os = "macos") {
+            names.push("yt-dlp_macos".to_string());
+        }
+        names.push(file_name.clone());
+        for name in &names {
+            paths.push((ToolSource::Bundled, resource_dir.join("binaries").join(name)));
+            paths.push((ToolSource::Bundled, resource_dir.join(name)));
+        }
+    }
+
+    if let Ok(app_data_dir) = app_handle.path().app_data_dir() {
+        paths.push((ToolSource::AppData, app_data_dir.join("binaries").join(&file_name)));
+    }
+
+    // Looked up directly rather than through a shell, so no terminal is spawned
+    if settings.search_path {
+        if let Some(path_var) = std::env::var_os("PATH") {
+            for dir in std::env::split_paths(&path_var).filter(|dir| dir.is_absolute()) {
+                paths.push((ToolSource::Path, dir.join(&file_name)));
+            }
+        }
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    paths.retain(|(_, path)| seen.insert(path.clone()));
+    paths
+}
+
+/// Finds a working binary for `tool`.
+///
+/// Locations are checked in order and each existing one is validated by running it;
+/// with `check_all` the remaining locations are still reported after a match.
+pub fn resolve(app_handle: &AppHandle, settings: &ToolchainSettings, tool: Tool, check_all: bool) -> ToolStatus {
+    let mut checked = Vec::new();
+    let mut found = false;
+
+    for (source, path) in candidate_paths(app_handle, settings, tool) {
+        let exists = path.is_file();
+        let (version, error) = if exists && (check_all || !found) {
+            match probe(&path, tool) {
+                Ok(version) => (Some(version), None),
+                Err(e) => (None, Some(e)),
+            }
+        } else {
+            (None, None)
+        };
+        found |= version.is_some();
+        checked.push(ToolCandidate {
+            source,
+            path: path.to_string_lossy().to_string(),
+            exists,
+            version,
+            error,
+        });
+        if found && !check_all {
+            break;
+        }
+    }
+
+    let mut status = ToolStatus {
+        tool,
+        path: None,
+        version: None,
+        source: None,
+        checked,
+    };
+    if let Some(selected) = status.selected().cloned() {
+        status.path = Some(selected.path);
+        status.version = selected.version;
+        status.source = Some(selected.source);
+    }
+    status
+}
+
+/// Resolves a tool and logs where it was found, or everywhere that was checked
+pub fn locate(app_handle: &AppHandle, settings: &ToolchainSettings, tool: Tool) -> ToolStatus {
+    let status = resolve(app_handle, settings, tool, false);
+    match (&status.path, &status.version) {
+        (Some(path), Some(version)) => {
+            println!("[Toolchain] Using {} {} at: {}", tool.label(), version, path);
+        }
+        _ => {
+            println!("[Toolchain] {} not found. Checked paths:", tool.label());
+            for candidate in &status.checked {
+                println!("  - {} ({})", candidate.path, candidate.error.as_deref().unwrap_or("missing"));
+            }
+        }
+    }
+    status
+}
+
+pub fn load_settings(app_handle: &AppHandle) -> ToolchainSettings {
+    app_handle
+        .try_state::<AppState>()
+        .and_then(|state| state.db.lock().ok().map(|db| ToolchainSettings::load(&db)))
+        .unwrap_or_default()
+}
+
+/// Full resolution report for `tool`, run off the async runtime since it spawns every candidate
+pub async fn status(app_handle: &AppHandle, tool: Tool) -> Result<ToolStatus, String> {
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let settings = load_settings(&app_handle);
+        resolve(&app_handle, &settings, tool, true)
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+// Tauri commands for tool discovery
+#[tauri::command]
+pub async fn get_toolchain_settings(state: State<'_, AppState>) -> Result<ToolchainSettings, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    Ok(ToolchainSettings::load(&db))
+}
+
+#[tauri::command]
+pub async fn set_toolchain_settings(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    settings: ToolchainSettings,
+) -> Result<(), String> {
+    settings.validate()?;
+    {
+        let serialized = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        db.save_setting(TOOLCHAIN_KEY, &serialized).map_err(|e| e.to_string())?;
+    }
+
+    Downloader::refresh(&app_handle).await?;
+    Ok(())
+}
+
+/// Looks for yt-dlp and ffmpeg again, e.g. after replacing a binary outside the app
+#[tauri::command]
+pub async fn refresh_toolchain(app_handle: AppHandle) -> Result<Vec<ToolStatus>, String> {
+    let downloader = Downloader::refresh(&app_handle).await?;
+    Ok(vec![downloader.yt_dlp_status().clone(), downloader.ffmpeg_status().clone()])
+}
+
+/// Every checked location and version for yt-dlp, ffmpeg and ffprobe
+#[tauri::command]
+pub async fn get_toolchain_status(app_handle: AppHandle) -> Result<Vec<ToolStatus>, String> {
+    let mut statuses = Vec::new();
+    for tool in [Tool::YtDlp, Tool::Ffmpeg, Tool::Ffprobe] {
+        statuses.push(status(&app_handle, tool).await?);
+    }
+    Ok(statuses)
+}
@@ -94,6 +94,7 @@ function DownloadCard({ item, onCancel, onDelete, onRetry }: DownloadCardProps)
                             <p className="text-sm text-muted-foreground">
                                 {item.platform || 'Unknown'} • {item.format || 'Best'}
                                 {item.size_bytes && ` • ${formatBytes(item.size_bytes)}`}
+                                {item.clip && ` • clip ${item.clip}`}
                             </p>
                         </div>
 
@@ -211,10 +212,9 @@ export function DownloadsPage() {
                 return newMap;
             });
 
-            // Update download status in database if completed or failed
+            // The backend records status changes in history; just refresh the list
             if (progress.status === 'completed' || progress.status === 'failed' || progress.status === 'cancelled') {
                 console.log(`[DownloadsPage] Download ${progress.id} status changed to: ${progress.status}`);
-                api.updateDownloadStatus(progress.id, progress.status);
                 // Refresh downloads list
                 setTimeout(loadDownloads, 500);
             }
@@ -247,7 +247,6 @@ export function DownloadsPage() {
         try {
             await api.cancelDownload(id);
             toast.success('Download cancelled');
-            await api.updateDownloadStatus(id, 'cancelled');
             loadDownloads();
         } catch (err) {
             toast.error('Failed to cancel download');
@@ -17,7 +17,7 @@ import { cn } from '@/lib/utils';
 import { staggerContainer, staggerItem, fadeInUp } from '@/lib/animations';
 import { use3DTilt } from '@/hooks/use3DTilt';
 import { toast } from 'sonner';
-import api, { MediaInfo, DownloadRequest, Download as DownloadType, generateDownloadId } from '@/services/api';
+import api, { MediaInfo, DownloadRequest, generateDownloadId, errorMessage } from '@/services/api';
 import { MediaInfoModal, DownloadOptions } from '@/components/MediaInfoModal';
 
 // Platform detection patterns
@@ -197,7 +197,7 @@ export function HomePage() {
             setShowModal(true);
             toast.success(`Found: ${info.title}`);
         } catch (err) {
-            const errorMsg = err instanceof Error ? err.message : 'Failed to fetch media info';
+            const errorMsg = errorMessage(err, 'Failed to fetch media info');
             setError(errorMsg);
             toast.error(errorMsg);
         } finally {
@@ -213,21 +213,6 @@ export function HomePage() {
         try {
             const downloadId = generateDownloadId();
 
-            // Create download record in database
-            const download: DownloadType = {
-                id: downloadId,
-                title: mediaInfo.title,
-                url: url,
-                format: options.audioOnly ? 'mp3' : options.quality,
-                path: downloadPath,
-                timestamp: Date.now(),
-                status: 'downloading',
-                platform: mediaInfo.platform,
-                thumbnail: mediaInfo.thumbnail,
-            };
-
-            await api.addDownload(download);
-
             // Start the actual download
             const request: DownloadRequest = {
                 id: downloadId,
@@ -238,6 +223,10 @@ export function HomePage() {
                 quality: options.quality,
                 embed_thumbnail: options.embedThumbnail,
                 embed_metadata: options.embedMetadata,
+                // The backend records the download in history as it progresses
+                title: mediaInfo.title,
+                thumbnail: mediaInfo.thumbnail,
+                platform: mediaInfo.platform,
             };
 
             await api.startDownload(request);
@@ -248,7 +237,7 @@ export function HomePage() {
             setMediaInfo(null);
             loadStats();
         } catch (err) {
-            const errorMsg = err instanceof Error ? err.message : 'Failed to start download';
+            const errorMsg = errorMessage(err, 'Failed to start download');
             toast.error(errorMsg);
         } finally {
             setIsDownloading(false);
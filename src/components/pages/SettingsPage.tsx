@@ -16,7 +16,7 @@ import { cn } from '@/lib/utils';
 import { staggerContainer, staggerItem, fadeInUp } from '@/lib/animations';
 import { toast } from 'sonner';
 import { open } from '@tauri-apps/plugin-dialog';
-import api, { YtDlpInfo } from '@/services/api';
+import api, { YtDlpInfo, errorMessage } from '@/services/api';
 
 interface SettingSectionProps {
     title: string;
@@ -150,7 +150,7 @@ export function SettingsPage() {
             const info = await api.checkYtDlp();
             setYtDlpInfo(info);
         } catch (err) {
-            setYtDlpError(err instanceof Error ? err.message : 'yt-dlp not found');
+            setYtDlpError(errorMessage(err, 'yt-dlp not found'));
         } finally {
             setYtDlpLoading(false);
         }
@@ -305,6 +305,7 @@ export function SettingsPage() {
                             <option value="opus">OPUS</option>
                             <option value="flac">FLAC</option>
                             <option value="wav">WAV</option>
+                            <option value="vorbis">Vorbis (OGG)</option>
                         </select>
                     </div>
                     <div className="py-3 border-b border-white/5">
@@ -13,6 +13,12 @@ export interface Download {
     size_bytes?: number;
     platform?: string;
     thumbnail?: string;
+    // Set on per-chapter files split from another download
+    parent_id?: string;
+    // Downloaded time ranges, e.g. "00:10:00–00:12:30"; absent for full downloads
+    clip?: string;
+    // Identity of the media regardless of URL spelling, e.g. "youtube:dQw4w9WgXcQ"
+    canonical_id?: string;
 }
 
 export interface SearchHistory {
@@ -29,6 +35,7 @@ export interface Setting {
 }
 
 export interface MediaInfo {
+    id?: string;
     title: string;
     duration?: number;
     thumbnail?: string;
@@ -40,6 +47,43 @@ export interface MediaInfo {
     like_count?: number;
     upload_date?: string;
     webpage_url?: string;
+    resolution?: string;
+    subtitles: SubtitleTrack[];
+    chapters: Chapter[];
+}
+
+export interface Chapter {
+    title: string;
+    // Seconds from the start of the media
+    start_time: number;
+    end_time: number;
+}
+
+export interface SubtitleTrack {
+    language: string;
+    name?: string;
+    automatic: boolean;
+    formats: string[];
+}
+
+export type SubtitleFormat = 'srt' | 'vtt' | 'ass';
+
+export interface SubtitleOptions {
+    // Language codes or yt-dlp patterns such as "en.*"; "all" selects every track
+    languages: string[];
+    include_automatic?: boolean;
+    format?: SubtitleFormat;
+    embed?: boolean;
+}
+
+export interface DownloadSubtitle {
+    id: number;
+    download_id: string;
+    language: string;
+    format: string;
+    path?: string;
+    embedded: boolean;
+    created_at: number;
 }
 
 export interface FormatInfo {
@@ -56,6 +100,29 @@ export interface FormatInfo {
     quality_label?: string;
 }
 
+export type DownloadErrorKind =
+    | 'unavailable'
+    | 'geo_restricted'
+    | 'login_required'
+    | 'rate_limited'
+    | 'unsupported_url'
+    | 'postprocess_failed'
+    | 'binary_missing'
+    | 'server_error'
+    | 'network'
+    | 'parse'
+    | 'invalid_request'
+    | 'duplicate'
+    | 'internal'
+    | 'other';
+
+export interface DownloadError {
+    kind: DownloadErrorKind;
+    message: string;
+    // Set for 'duplicate': the completed download this one repeats
+    existing_id?: string;
+}
+
 export interface DownloadProgress {
     id: string;
     progress: number;
@@ -65,6 +132,170 @@ export interface DownloadProgress {
     downloaded_bytes?: number;
     total_bytes?: number;
     filename?: string;
+    error?: DownloadError;
+}
+
+export type AudioFormat = 'mp3' | 'm4a' | 'aac' | 'opus' | 'flac' | 'wav' | 'vorbis';
+
+// Constant bitrate in kbps, or an encoder VBR level from 0 (best) to 10
+export type AudioQuality =
+    | { mode: 'bitrate'; kbps: number }
+    | { mode: 'vbr'; level: number };
+
+export type CollisionPolicy = 'skip' | 'overwrite' | 'append_suffix';
+
+// `template` uses {title}, {id}, {uploader}, {upload_date}, {playlist}, {playlist_index},
+// {resolution} and {platform}; `/` creates subfolders and the extension is added automatically
+export interface NamingSettings {
+    template: string;
+    collision_policy: CollisionPolicy;
+}
+
+// Seconds, or "HH:MM:SS(.ms)" / "MM:SS" timestamps; omit `end` to run to the end
+export interface ClipRange {
+    start: number | string;
+    end?: number | string;
+}
+
+export type IpVersion = 'v4' | 'v6';
+
+// Also used for per-request overrides: set fields replace the saved ones, headers are merged
+export interface NetworkSettings {
+    proxy?: string; // e.g. "socks5://127.0.0.1:1080"; "" forces a direct connection
+    user_agent?: string;
+    headers?: Record<string, string>;
+    source_address?: string;
+    ip_version?: IpVersion;
+    socket_timeout_secs?: number;
+}
+
+export type CookieJarStatus = 'valid' | 'expiring_soon' | 'expired';
+
+export type CookieBrowser =
+    | 'brave'
+    | 'chrome'
+    | 'chromium'
+    | 'edge'
+    | 'firefox'
+    | 'opera'
+    | 'safari'
+    | 'vivaldi'
+    | 'whale';
+
+// A stored cookie file, used automatically for the domain and its subdomains
+export interface CookieJar {
+    domain: string;
+    path: string;
+    cookie_count: number;
+    session_count: number;
+    expired_count: number;
+    expires_at?: number; // ms, when the last persistent cookie expires
+    status: CookieJarStatus;
+    updated_at?: number;
+}
+
+export interface CredentialStoreStatus {
+    initialized: boolean; // a passphrase has been set up
+    unlocked: boolean;
+}
+
+// Passwords and tokens are never sent back to the frontend
+export interface CredentialSummary {
+    machine: string; // yt-dlp netrc machine name, e.g. "youtube" or "vimeo"
+    login: string;
+    updated_at: number;
+}
+
+export type BinaryTool = 'yt_dlp' | 'ffmpeg' | 'ffprobe';
+
+// A release folder (http(s) URL, file:// URL or local path) with a sha256sum-style checksum file
+export interface BinarySource {
+    base: string;
+    checksums: string;
+}
+
+export interface BinarySourceSettings {
+    yt_dlp: BinarySource;
+    ffmpeg?: BinarySource; // serves plain ffmpeg and ffprobe binaries
+}
+
+export interface BinaryRecord {
+    version: string;
+    sha256: string;
+    source: string;
+    installed_at: number;
+}
+
+export interface BinaryStatus {
+    tool: BinaryTool;
+    path?: string;
+    current?: BinaryRecord;
+    previous?: BinaryRecord;
+    can_rollback: boolean;
+    installable: boolean;
+}
+
+export type InstallStage = 'downloading' | 'verifying' | 'installing' | 'completed' | 'rolled_back' | 'failed';
+
+// Payload of the binary-install-progress event
+export interface BinaryInstallProgress {
+    tool: BinaryTool;
+    stage: InstallStage;
+    downloaded_bytes: number;
+    total_bytes?: number;
+    version?: string;
+    error?: string;
+}
+
+export interface MediaCacheSettings {
+    // How long extracted info is reused; 0 turns the cache off
+    ttl_secs: number;
+}
+
+export interface MediaCacheEntry {
+    url: string;
+    title?: string;
+    fetched_at: number;
+    expires_at: number;
+    size_bytes: number;
+}
+
+export type DuplicatePolicy = 'allow' | 'warn' | 'skip';
+
+export interface DuplicateSettings {
+    policy: DuplicatePolicy;
+}
+
+// Payload of the duplicate-download event
+export interface DuplicateWarning {
+    id: string;
+    url: string;
+    canonical_id: string;
+    existing: Download;
+}
+
+export type MergeStrategy = 'skip_existing' | 'overwrite' | 'newest_wins';
+
+export interface ExportSummary {
+    path: string;
+    downloads: number;
+    search_history: number;
+    settings: number;
+}
+
+export interface ImportCounts {
+    inserted: number;
+    updated: number;
+    skipped: number;
+}
+
+export interface ImportReport {
+    exported_at: number;
+    downloads: ImportCounts;
+    search_history: ImportCounts;
+    settings: ImportCounts;
+    // Settings have no timestamp, so newest_wins merges them as skip_existing
+    settings_strategy: MergeStrategy;
 }
 
 export interface DownloadRequest {
@@ -73,15 +304,169 @@ export interface DownloadRequest {
     output_path: string;
     format?: string;
     audio_only: boolean;
+    // Fall back to the audio_format / audio_bitrate settings when omitted
+    audio_format?: AudioFormat;
+    audio_quality?: AudioQuality;
     quality?: string;
+    // Bytes per second; overrides the global limit and its schedule
+    rate_limit?: number;
+    network?: NetworkSettings;
+    clip_ranges?: ClipRange[];
+    subtitles?: SubtitleOptions;
     embed_thumbnail: boolean;
     embed_metadata: boolean;
+    embed_chapters?: boolean;
+    // Also write one file per chapter, each recorded as a download linked to this one
+    split_chapters?: boolean;
+    title?: string;
+    thumbnail?: string;
+    platform?: string;
+    filename_template?: string;
+    collision_policy?: CollisionPolicy;
+    playlist_title?: string;
+    playlist_index?: number;
+    output_name?: string;
+}
+
+export interface PlaylistEntry {
+    id: string;
+    title: string;
+    url: string;
+    duration?: number;
+    thumbnail?: string;
+    index: number;
+}
+
+export interface PlaylistInfo {
+    id?: string;
+    title: string;
+    uploader?: string;
+    platform: string;
+    entry_count?: number;
+    entries: PlaylistEntry[];
+    page: number;
+    page_size: number;
+    has_more: boolean;
+}
+
+// A selected entry that was not queued
+export interface PlaylistEntryFailure {
+    index: number;
+    url: string;
+    error: DownloadError;
+}
+
+export interface PlaylistEnqueueReport {
+    // Ids of the queued downloads, in entry order
+    queued: string[];
+    failed: PlaylistEntryFailure[];
+}
+
+export type BatchLineStatus = 'accepted' | 'rejected' | 'duplicate';
+
+export interface BatchLineReport {
+    line: number;
+    url?: string;
+    status: BatchLineStatus;
+    reason?: string;
+    id?: string;
+}
+
+export interface BatchImportReport {
+    entries: BatchLineReport[];
+    accepted: number;
+    rejected: number;
+    duplicates: number;
+    enqueued: boolean;
+}
+
+export interface QueueItem {
+    id: string;
+    request: DownloadRequest;
+    state: string;
+    attempts: number;
+    last_error?: string;
+    created_at: number;
+    updated_at: number;
+}
+
+export type RetryableClass = 'rate_limited' | 'server_error' | 'network';
+
+export interface RetryPolicy {
+    max_attempts: number;
+    initial_delay_secs: number;
+    max_delay_secs: number;
+    multiplier: number;
+    retry_on: RetryableClass[];
+}
+
+export interface DownloadAttempt {
+    id: number;
+    download_id: string;
+    attempt: number;
+    started_at: number;
+    finished_at?: number;
+    outcome?: string;
+    reason?: string;
+}
+
+export interface ScheduledJob {
+    id: string;
+    platform: string;
+    // Bytes per second the job was launched with
+    rate_limit?: number;
+}
+
+// Limits are in bytes per second; leave them empty for unlimited
+export interface BandwidthWindow {
+    start: string; // "HH:MM", local time
+    end: string;   // "HH:MM", exclusive; may wrap past midnight
+    limit?: number;
+}
+
+export interface BandwidthSettings {
+    limit?: number;
+    schedule: BandwidthWindow[];
+}
+
+export interface SchedulerStatus {
+    max_concurrent: number;
+    platform_limits: Record<string, number>;
+    running: ScheduledJob[];
+    pending: ScheduledJob[];
+}
+
+export type ToolSource = 'custom' | 'bundled' | 'app_data' | 'path';
+
+// One location checked while resolving a tool
+export interface ToolCandidate {
+    source: ToolSource;
+    path: string;
+    exists: boolean;
+    version?: string;
+    error?: string;
+}
+
+export interface ToolStatus {
+    tool: BinaryTool;
+    path?: string; // the binary that will be used
+    version?: string;
+    source?: ToolSource;
+    checked: ToolCandidate[];
+}
+
+export interface ToolchainSettings {
+    yt_dlp_path?: string; // binary or folder, checked first
+    ffmpeg_path?: string; // binary or folder with ffmpeg and ffprobe
+    search_path: boolean; // fall back to tools on the system PATH
 }
 
 export interface YtDlpInfo {
     version: string;
     path: string;
     is_embedded: boolean;
+    source: ToolSource;
+    checked: ToolCandidate[];
 }
 
 // Download API
@@ -155,8 +540,8 @@ export const api = {
     },
 
     // Media Info & Downloading
-    async getMediaInfo(url: string): Promise<MediaInfo> {
-        return invoke('get_media_info', { url });
+    async getMediaInfo(url: string, network?: NetworkSettings): Promise<MediaInfo> {
+        return invoke('get_media_info', { url, network });
     },
 
     async startDownload(request: DownloadRequest): Promise<void> {
@@ -167,15 +552,291 @@ export const api = {
         return invoke('cancel_download', { id });
     },
 
+    async pauseDownload(id: string): Promise<void> {
+        return invoke('pause_download', { id });
+    },
+
+    async resumeDownload(id: string): Promise<void> {
+        return invoke('resume_download', { id });
+    },
+
+    // Playlists
+    async getPlaylistInfo(url: string, page?: number, pageSize?: number): Promise<PlaylistInfo> {
+        return invoke('get_playlist_info', { url, page, pageSize });
+    },
+
+    // `template` supplies the download options; its id and url are replaced per entry
+    async enqueuePlaylistEntries(
+        playlistTitle: string,
+        entries: PlaylistEntry[],
+        template: DownloadRequest,
+    ): Promise<PlaylistEnqueueReport> {
+        return invoke('enqueue_playlist_entries', { playlistTitle, entries, template });
+    },
+
+    // File naming
+    async getNamingSettings(): Promise<NamingSettings> {
+        return invoke('get_naming_settings');
+    },
+
+    async setNamingSettings(settings: NamingSettings): Promise<void> {
+        return invoke('set_naming_settings', { settings });
+    },
+
+    // Returns the path relative to the output folder, without extension
+    async previewFilenameTemplate(
+        template: string,
+        info: MediaInfo,
+        playlistTitle?: string,
+        playlistIndex?: number,
+    ): Promise<string> {
+        return invoke('preview_filename_template', { template, info, playlistTitle, playlistIndex });
+    },
+
+    // Batch import (.txt, .csv or .json); `template` supplies the default options
+    async importBatch(path: string, template: DownloadRequest, dryRun = false): Promise<BatchImportReport> {
+        return invoke('import_batch', { path, template, dryRun });
+    },
+
+    // Download Queue
+    async getDownloadQueue(): Promise<QueueItem[]> {
+        return invoke('get_download_queue');
+    },
+
+    async removeFromQueue(id: string): Promise<void> {
+        return invoke('remove_from_queue', { id });
+    },
+
+    async clearFinishedQueue(): Promise<void> {
+        return invoke('clear_finished_queue');
+    },
+
+    // Retries
+    async getRetryPolicy(): Promise<RetryPolicy> {
+        return invoke('get_retry_policy');
+    },
+
+    async setRetryPolicy(policy: RetryPolicy): Promise<void> {
+        return invoke('set_retry_policy', { policy });
+    },
+
+    async getDownloadAttempts(id: string): Promise<DownloadAttempt[]> {
+        return invoke('get_download_attempts', { id });
+    },
+
+    // Per-chapter downloads split from a download
+    async getDownloadChapters(id: string): Promise<Download[]> {
+        return invoke('get_download_chapters', { id });
+    },
+
+    // Subtitle files written or embedded by a download
+    async getDownloadSubtitles(id: string): Promise<DownloadSubtitle[]> {
+        return invoke('get_download_subtitles', { id });
+    },
+
+    // Cookie files
+    async listCookieJars(): Promise<CookieJar[]> {
+        return invoke('list_cookie_jars');
+    },
+
+    // Without a domain, the site most of the cookies belong to is used
+    async importCookieFile(path: string, domain?: string): Promise<CookieJar> {
+        return invoke('import_cookie_file', { path, domain });
+    },
+
+    async importBrowserCookies(browser: CookieBrowser, domain: string): Promise<CookieJar> {
+        return invoke('import_browser_cookies', { browser, domain });
+    },
+
+    async deleteCookieJar(domain: string): Promise<void> {
+        return invoke('delete_cookie_jar', { domain });
+    },
+
+    // Credential store; logins are encrypted with a key derived from the passphrase
+    async getCredentialStoreStatus(): Promise<CredentialStoreStatus> {
+        return invoke('get_credential_store_status');
+    },
+
+    // Sets the store up with this passphrase the first time
+    async unlockCredentialStore(passphrase: string): Promise<void> {
+        return invoke('unlock_credential_store', { passphrase });
+    },
+
+    async lockCredentialStore(): Promise<void> {
+        return invoke('lock_credential_store');
+    },
+
+    async changeCredentialPassphrase(current: string, newPassphrase: string): Promise<void> {
+        return invoke('change_credential_passphrase', { current, new: newPassphrase });
+    },
+
+    // Deletes the passphrase and every stored login
+    async resetCredentialStore(): Promise<void> {
+        return invoke('reset_credential_store');
+    },
+
+    async listCredentials(): Promise<CredentialSummary[]> {
+        return invoke('list_credentials');
+    },
+
+    async saveCredential(machine: string, login: string, password: string): Promise<void> {
+        return invoke('save_credential', { machine, login, password });
+    },
+
+    async deleteCredential(machine: string): Promise<void> {
+        return invoke('delete_credential', { machine });
+    },
+
+    // Managed yt-dlp / ffmpeg binaries
+    async getBinarySources(): Promise<BinarySourceSettings> {
+        return invoke('get_binary_sources');
+    },
+
+    async setBinarySources(settings: BinarySourceSettings): Promise<void> {
+        return invoke('set_binary_sources', { settings });
+    },
+
+    async getBinariesStatus(): Promise<BinaryStatus[]> {
+        return invoke('get_binaries_status');
+    },
+
+    // Progress is reported through binary-install-progress events
+    async installBinary(tool: BinaryTool): Promise<BinaryRecord> {
+        return invoke('install_binary', { tool });
+    },
+
+    async rollbackBinary(tool: BinaryTool): Promise<BinaryRecord | null> {
+        return invoke('rollback_binary', { tool });
+    },
+
+    // Tool discovery
+    async getToolchainSettings(): Promise<ToolchainSettings> {
+        return invoke('get_toolchain_settings');
+    },
+
+    async setToolchainSettings(settings: ToolchainSettings): Promise<void> {
+        return invoke('set_toolchain_settings', { settings });
+    },
+
+    async getToolchainStatus(): Promise<ToolStatus[]> {
+        return invoke('get_toolchain_status');
+    },
+
+    // Looks for yt-dlp and ffmpeg again; returns what will be used from now on
+    async refreshToolchain(): Promise<ToolStatus[]> {
+        return invoke('refresh_toolchain');
+    },
+
+    // Media info cache
+    async getMediaCacheSettings(): Promise<MediaCacheSettings> {
+        return invoke('get_media_cache_settings');
+    },
+
+    async setMediaCacheSettings(settings: MediaCacheSettings): Promise<void> {
+        return invoke('set_media_cache_settings', { settings });
+    },
+
+    async getMediaCache(): Promise<MediaCacheEntry[]> {
+        return invoke('get_media_cache');
+    },
+
+    // Removes the entry for one URL, or everything; returns how many entries were removed
+    async purgeMediaCache(url?: string): Promise<number> {
+        return invoke('purge_media_cache', { url });
+    },
+
+    // Duplicate detection
+    async getDuplicateSettings(): Promise<DuplicateSettings> {
+        return invoke('get_duplicate_settings');
+    },
+
+    async setDuplicateSettings(settings: DuplicateSettings): Promise<void> {
+        return invoke('set_duplicate_settings', { settings });
+    },
+
+    // The completed download a URL would repeat, if any
+    async findDuplicateDownload(url: string): Promise<Download | null> {
+        return invoke('find_duplicate_download', { url });
+    },
+
+    // Database export and import
+    async exportDatabase(path: string): Promise<ExportSummary> {
+        return invoke('export_database', { path });
+    },
+
+    // Rows are matched by id and settings by key; newest_wins keeps existing settings
+    async importDatabase(path: string, strategy: MergeStrategy): Promise<ImportReport> {
+        return invoke('import_database', { path, strategy });
+    },
+
+    // Network settings
+    async getNetworkSettings(): Promise<NetworkSettings> {
+        return invoke('get_network_settings');
+    },
+
+    async setNetworkSettings(settings: NetworkSettings): Promise<void> {
+        return invoke('set_network_settings', { settings });
+    },
+
+    // Bandwidth throttling
+    async getBandwidthSettings(): Promise<BandwidthSettings> {
+        return invoke('get_bandwidth_settings');
+    },
+
+    async setBandwidthSettings(settings: BandwidthSettings): Promise<void> {
+        return invoke('set_bandwidth_settings', { settings });
+    },
+
+    async getCurrentBandwidthLimit(): Promise<number | null> {
+        return invoke('get_current_bandwidth_limit');
+    },
+
+    // Scheduler
+    async getSchedulerStatus(): Promise<SchedulerStatus> {
+        return invoke('get_scheduler_status');
+    },
+
+    async setMaxConcurrentDownloads(limit: number): Promise<void> {
+        return invoke('set_max_concurrent_downloads', { limit });
+    },
+
+    async setPlatformConcurrencyLimit(platform: string, limit?: number): Promise<void> {
+        return invoke('set_platform_concurrency_limit', { platform, limit });
+    },
+
     // Event listeners
     onDownloadProgress(callback: (progress: DownloadProgress) => void): Promise<UnlistenFn> {
         return listen<DownloadProgress>('download-progress', (event) => {
             callback(event.payload);
         });
     },
+
+    onBinaryInstallProgress(callback: (progress: BinaryInstallProgress) => void): Promise<UnlistenFn> {
+        return listen<BinaryInstallProgress>('binary-install-progress', (event) => {
+            callback(event.payload);
+        });
+    },
+
+    // Fired when a download is queued despite repeating a completed one
+    onDuplicateDownload(callback: (warning: DuplicateWarning) => void): Promise<UnlistenFn> {
+        return listen<DuplicateWarning>('duplicate-download', (event) => {
+            callback(event.payload);
+        });
+    },
 };
 
 // Helper functions
+export function isDownloadError(err: unknown): err is DownloadError {
+    return typeof err === 'object' && err !== null && 'kind' in err && 'message' in err;
+}
+
+export function errorMessage(err: unknown, fallback: string): string {
+    if (isDownloadError(err)) return err.message;
+    if (err instanceof Error) return err.message;
+    if (typeof err === 'string') return err;
+    return fallback;
+}
+
 export function formatBytes(bytes: number): string {
     if (bytes === 0) return '0 B';
     const k = 1024;